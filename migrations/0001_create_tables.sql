@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS channels (
+    channel_id BIGINT NOT NULL PRIMARY KEY,
+    channel_name TEXT NOT NULL,
+    guild_id BIGINT NOT NULL,
+    guild_name TEXT NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL,
+    added_by BIGINT NOT NULL,
+    suppress BOOLEAN NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS anilist (
+    anilist_id BIGINT NOT NULL,
+    anilist_name TEXT NOT NULL,
+    site_url TEXT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL,
+    added_by BIGINT NOT NULL,
+    PRIMARY KEY(anilist_id, channel_id),
+    FOREIGN KEY (channel_id) REFERENCES channels (channel_id)
+);
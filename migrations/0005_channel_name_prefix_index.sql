@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS channels_channel_name_prefix ON channels (lower(channel_name) text_pattern_ops);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS guild_defaults (
+    guild_id BIGINT NOT NULL PRIMARY KEY,
+    suppress BOOLEAN NOT NULL
+);
@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    ts TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    affected_id BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_ts ON audit_log (ts DESC);
+CREATE INDEX IF NOT EXISTS audit_log_affected_id ON audit_log (affected_id, id DESC);
+
+-- Append-only: rows can be inserted but never changed or deleted one by one.
+-- TRUNCATE is left alone so the tests can start from an empty log;
+-- /admin/reset doesn't touch this table.
+CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_append_only ON audit_log;
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
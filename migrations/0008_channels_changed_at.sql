@@ -0,0 +1,36 @@
+-- When a channel row last disappeared outright, so GET /channel's
+-- Last-Modified also moves on hard deletes, which leave no timestamp on the
+-- table itself. One row, NULL until the first delete.
+CREATE TABLE IF NOT EXISTS channels_changed_at (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    changed_at TIMESTAMPTZ
+);
+
+INSERT INTO channels_changed_at DEFAULT VALUES ON CONFLICT DO NOTHING;
+
+CREATE OR REPLACE FUNCTION channels_deleted() RETURNS trigger AS $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM deleted) THEN
+        UPDATE channels_changed_at SET changed_at = NOW();
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION channels_truncated() RETURNS trigger AS $$
+BEGIN
+    UPDATE channels_changed_at SET changed_at = NOW();
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS channels_deleted ON channels;
+CREATE TRIGGER channels_deleted
+    AFTER DELETE ON channels
+    REFERENCING OLD TABLE AS deleted
+    FOR EACH STATEMENT EXECUTE FUNCTION channels_deleted();
+
+DROP TRIGGER IF EXISTS channels_truncated ON channels;
+CREATE TRIGGER channels_truncated
+    AFTER TRUNCATE ON channels
+    FOR EACH STATEMENT EXECUTE FUNCTION channels_truncated();
@@ -0,0 +1,5 @@
+-- How each logged write ended: 00000 when it went through, otherwise the
+-- SQLSTATE Postgres rejected it with. Rows written before this column
+-- existed were all successful writes.
+ALTER TABLE audit_log ADD COLUMN IF NOT EXISTS sqlstate TEXT NOT NULL DEFAULT '00000';
+ALTER TABLE audit_log ALTER COLUMN sqlstate DROP DEFAULT;
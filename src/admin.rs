@@ -0,0 +1,101 @@
+use axum::{extract::State, http::StatusCode, Json};
+use deadpool_postgres::Pool;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    util::{
+        db::{execute, with_transaction},
+        error_handling::internal_error,
+    },
+    Message,
+};
+
+// Only routed when ENABLE_ADMIN_ENDPOINTS=true, otherwise it falls through to
+// the usual 404. Meant for integration tests against a disposable database.
+#[utoipa::path(
+    post,
+    path = "/admin/reset",
+    tag = "admin",
+    responses(
+        (status = 204, description = "Every channel and subscription deleted"),
+        (status = 404, description = "Admin endpoints are disabled", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn reset(
+    State(pool): State<Arc<Pool>>,
+) -> Result<StatusCode, (StatusCode, Json<Message>)> {
+    with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            execute(tx, "TRUNCATE anilist, channels", &[])
+                .await
+                .map_err(internal_error)?;
+            Ok(())
+        })
+    })
+    .await?;
+
+    tracing::warn!("All channels and subscriptions were deleted via /admin/reset");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// How long a soft-deleted channel is kept before POST /admin/purge removes
+// it for good, from PURGE_RETENTION_DAYS.
+#[derive(Clone, Copy)]
+pub struct PurgeRetention(pub Duration);
+
+impl Default for PurgeRetention {
+    fn default() -> Self {
+        PurgeRetention(Duration::from_secs(30 * 24 * 60 * 60))
+    }
+}
+
+// Unlike reset this is routed everywhere, it only removes channels that
+// were already deleted. Their subscriptions go first, in the same
+// transaction.
+#[utoipa::path(
+    post,
+    path = "/admin/purge",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Number of soft-deleted channels removed for good, in data", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn purge(
+    State(pool): State<Arc<Pool>>,
+    State(PurgeRetention(retention)): State<PurgeRetention>,
+) -> Result<Json<Message>, (StatusCode, Json<Message>)> {
+    let retention = retention.as_secs_f64();
+    let purged = with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            execute(
+                tx,
+                "DELETE FROM anilist WHERE channel_id IN (SELECT channel_id FROM channels WHERE deleted_at <= NOW() - make_interval(secs => $1))",
+                &[&retention],
+            )
+            .await
+            .map_err(internal_error)?;
+
+            execute(
+                tx,
+                "DELETE FROM channels WHERE deleted_at <= NOW() - make_interval(secs => $1)",
+                &[&retention],
+            )
+            .await
+            .map_err(internal_error)
+        })
+    })
+    .await?;
+
+    tracing::info!("Purged {purged} soft-deleted channels");
+
+    let msg = Message {
+        message: format!("Purged {purged} channels"),
+        data: Some(vec![purged.to_string()]),
+        ..Default::default()
+    };
+
+    Ok(Json(msg))
+}
@@ -1,7 +1,27 @@
-use axum::{http::StatusCode, Json};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    Json,
+};
+use deadpool_postgres::{GenericClient, Pool};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio_postgres::Row;
+use url::Url;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::Message;
+use crate::{
+    channel::{escape_like, validate_offset, LIST_MAX_LIMIT, X_TOTAL_COUNT},
+    types::{ChannelId, UserId},
+    util::{
+        db::{execute, query, query_one, with_retry, Replica},
+        error_handling::{internal_error, pool_error},
+        json::ValidatedJson,
+        path::ValidatedPath,
+        validation::FieldErrors,
+    },
+    Message,
+};
 
 // CREATE TABLE anilist (
 //     anilist_id BIGINT NOT NULL,
@@ -14,41 +34,815 @@ use crate::Message;
 //     FOREIGN KEY (channel_id) REFERENCES channels (channel_id)
 //   )
 
-#[derive(Debug, Serialize, Deserialize)]
+pub(crate) const INSERT_USER: &str = "INSERT INTO anilist VALUES ($1, $2, $3, $4, NOW(), $5)";
+
+// Re-adding a user refreshes their name and URL. added_at and added_by keep
+// describing the original subscription.
+const UPSERT_USER: &str = "INSERT INTO anilist VALUES ($1, $2, $3, $4, NOW(), $5) ON CONFLICT (anilist_id, channel_id) DO UPDATE SET anilist_name = EXCLUDED.anilist_name, site_url = EXCLUDED.site_url RETURNING anilist_id, anilist_name, site_url, channel_id, added_by, (xmax = 0) AS inserted";
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserData {
-    anilist_id: u64, 
-    anilist_name: String,
-    site_url: String,
-    channel_id: u64,
-    added_by: u64,
+    pub(crate) anilist_id: i64,
+    pub(crate) anilist_name: String,
+    pub(crate) site_url: String,
+    pub(crate) channel_id: ChannelId,
+    pub(crate) added_by: UserId,
+}
+
+impl From<&Row> for UserData {
+    fn from(row: &Row) -> Self {
+        UserData {
+            anilist_id: row.get("anilist_id"),
+            anilist_name: row.get("anilist_name"),
+            site_url: row.get("site_url"),
+            channel_id: row.get("channel_id"),
+            added_by: row.get("added_by"),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Same paging as GET /channel, without the cursor.
+#[derive(Deserialize, IntoParams)]
+pub struct ListOptions {
+    /// Substring of the AniList name, matched case-insensitively
+    name: Option<String>,
+    /// Clamped to 1..=1000
+    #[serde(default = "default_list_limit")]
+    limit: i64,
+    /// Must not be negative
+    #[serde(default)]
+    offset: i64,
+}
+
+fn default_list_limit() -> i64 {
+    50
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SubscriptionCount {
+    channel_id: ChannelId,
+    count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[allow(clippy::upper_case_acronyms)]
 #[serde(tag = "type", content = "value")]
 pub enum User {
     NAME(String),
     URL(String),
 }
 
-pub async fn add_user (
-    Json(payload): Json<User>,
-) -> (StatusCode, Json<Message>) {
+// `user` is flattened, so the body looks like
+// {"channel_id": 1, "added_by": 2, "type": "URL", "value": "https://anilist.co/user/3/"}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddUser {
+    channel_id: ChannelId,
+    added_by: UserId,
+    /// Display name to store, defaults to the AniList user ID
+    anilist_name: Option<String>,
+    #[serde(flatten)]
+    user: User,
+}
+
+// Names the subscription to remove, e.g.
+// {"channel_id": 1, "type": "URL", "value": "https://anilist.co/user/3/"}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RemoveUser {
+    channel_id: ChannelId,
+    #[serde(flatten)]
+    user: User,
+}
+
+#[utoipa::path(
+    post,
+    path = "/anilist",
+    tag = "anilist",
+    request_body = AddUser,
+    responses(
+        (status = 200, description = "User was already subscribed, name and URL refreshed", body = UserData),
+        (status = 201, description = "User subscribed to the channel", body = UserData),
+        (status = 400, description = "Malformed body or not an AniList user URL", body = Message),
+        (status = 422, description = "Invalid fields, one per entry in data", body = Message),
+        (status = 501, description = "Adding by name needs an AniList lookup", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn add_user(
+    State(pool): State<Arc<Pool>>,
+    ValidatedJson(payload): ValidatedJson<AddUser>,
+) -> Result<(StatusCode, HeaderMap, Json<UserData>), (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .snowflake("channel_id", payload.channel_id)
+        .snowflake("added_by", payload.added_by)
+        .finish()?;
+
+    let site_url = match payload.user {
+        User::URL(url) => url,
+        User::NAME(_) => {
+            let msg = Message {
+                message: "Adding users by name is not supported yet, send their AniList URL"
+                    .to_string(),
+                code: Some("not_implemented".to_string()),
+                ..Default::default()
+            };
+
+            return Err((StatusCode::NOT_IMPLEMENTED, Json(msg)));
+        }
+    };
+    let anilist_id = parse_site_url(&site_url)?;
+
+    let user = UserData {
+        anilist_id,
+        anilist_name: payload
+            .anilist_name
+            .unwrap_or_else(|| anilist_id.to_string()),
+        site_url,
+        channel_id: payload.channel_id,
+        added_by: payload.added_by,
+    };
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
 
-    let msg = match payload {
-        User::NAME(name) => format!("Added user by AniList name: {}", name),
-        User::URL(url) => format!("Added user by AniList URL: {}", url),
+    let row = query_one(
+        &con,
+        UPSERT_USER,
+        &[
+            &user.anilist_id,
+            &user.anilist_name,
+            &user.site_url,
+            &user.channel_id,
+            &user.added_by,
+        ],
+    )
+    .await
+    .map_err(internal_error)?;
+    let status = if row.get("inserted") {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
     };
+    let user = UserData::from(&row);
+
+    let mut headers = HeaderMap::new();
+    let location = format!("/channel/{}/anilist", user.channel_id);
+    headers.insert(
+        header::LOCATION,
+        HeaderValue::from_str(&location).expect("channel URL is a valid header value"),
+    );
+
+    Ok((status, headers, Json(user)))
+}
+
+// Subscribes `user` to `channel_id`, whatever channel_id the payload named.
+// Callers report bad site_urls with the rest of their FieldErrors first, the
+// check here only keeps one from slipping through.
+#[allow(clippy::result_large_err)]
+pub(crate) async fn insert_user(
+    client: &impl GenericClient,
+    user: &UserData,
+    channel_id: ChannelId,
+) -> Result<u64, (StatusCode, Json<Message>)> {
+    validate_site_url(&user.site_url)?;
+
+    execute(
+        client,
+        INSERT_USER,
+        &[
+            &user.anilist_id,
+            &user.anilist_name,
+            &user.site_url,
+            &channel_id,
+            &user.added_by,
+        ],
+    )
+    .await
+    .map_err(internal_error)
+}
 
+// Only https links to anilist.co are stored, so whatever we later show to
+// users is a real AniList page.
+#[allow(clippy::result_large_err)]
+pub fn validate_site_url(site_url: &str) -> Result<Url, (StatusCode, Json<Message>)> {
+    let valid = Url::parse(site_url).ok().filter(|url| {
+        url.scheme() == "https" && matches!(url.host_str(), Some("anilist.co" | "www.anilist.co"))
+    });
+
+    valid.ok_or_else(|| site_url_message(site_url))
+}
+
+// Extracts the numeric user ID from a profile URL such as
+// https://anilist.co/user/12345/ so we don't need a GraphQL lookup.
+#[allow(clippy::result_large_err)]
+pub fn parse_site_url(site_url: &str) -> Result<i64, (StatusCode, Json<Message>)> {
+    let url = validate_site_url(site_url)?;
+
+    // A trailing slash shows up as an empty last segment.
+    let segments: Vec<&str> = url.path_segments().into_iter().flatten().collect();
+    let id = match segments[..] {
+        ["user", id] | ["user", id, ""] => id.parse::<i64>().ok().filter(|&id| id > 0),
+        _ => None,
+    };
+
+    id.ok_or_else(|| site_url_message(site_url))
+}
+
+fn site_url_message(site_url: &str) -> (StatusCode, Json<Message>) {
     let msg = Message {
-        message: format!("Added {msg}"),
+        message: format!(
+            "{site_url} is not an AniList user URL like https://anilist.co/user/12345/"
+        ),
+        code: Some("validation_error".to_string()),
+        ..Default::default()
     };
 
-    (StatusCode::OK, Json(msg))
+    (StatusCode::BAD_REQUEST, Json(msg))
 }
 
-pub async fn remove_user (
-    Json(payload): Json<User>,
-) -> (StatusCode, Json<Message>) {
+// By URL matches the AniList user ID, by name the stored display name,
+// ignoring case.
+#[utoipa::path(
+    delete,
+    path = "/anilist",
+    tag = "anilist",
+    request_body = RemoveUser,
+    responses(
+        (status = 204, description = "User unsubscribed from the channel"),
+        (status = 400, description = "Malformed body or not an AniList user URL", body = Message),
+        (status = 404, description = "User isn't subscribed to the channel", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn remove_user(
+    State(pool): State<Arc<Pool>>,
+    ValidatedJson(payload): ValidatedJson<RemoveUser>,
+) -> Result<StatusCode, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let (removed, user) = match &payload.user {
+        User::URL(url) => {
+            let anilist_id = parse_site_url(url)?;
+            let removed = execute(
+                &con,
+                "DELETE FROM anilist WHERE channel_id = $1 AND anilist_id = $2",
+                &[&payload.channel_id, &anilist_id],
+            )
+            .await
+            .map_err(internal_error)?;
+            (removed, url)
+        }
+        User::NAME(name) => {
+            let removed = execute(
+                &con,
+                "DELETE FROM anilist WHERE channel_id = $1 AND lower(anilist_name) = lower($2)",
+                &[&payload.channel_id, name],
+            )
+            .await
+            .map_err(internal_error)?;
+            (removed, name)
+        }
+    };
+
+    if removed == 0 {
+        let msg = Message {
+            message: format!("{user} isn't subscribed to {}", payload.channel_id),
+            code: Some("not_found".to_string()),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::NOT_FOUND, Json(msg)));
+    }
 
-    (StatusCode::OK, Json())
-}
\ No newline at end of file
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/{channelid}/anilist",
+    tag = "anilist",
+    params(("channelid" = i64, Path, description = "Discord channel ID"), ListOptions),
+    responses(
+        (status = 200, description = "AniList users subscribed to the channel, ordered by anilist_id", body = Vec<UserData>, headers(("X-Total-Count" = i64, description = "Subscriptions matching name across all pages"))),
+        (status = 400, description = "A negative offset", body = Message),
+    )
+)]
+pub async fn list_by_channel(
+    State(Replica(pool)): State<Replica>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+    Query(options): Query<ListOptions>,
+) -> Result<(HeaderMap, Json<Vec<UserData>>), (StatusCode, Json<Message>)> {
+    validate_offset(options.offset)?;
+    let limit = options.limit.clamp(1, LIST_MAX_LIMIT);
+    let name = options.name.as_deref().map(escape_like);
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(
+        &con,
+        "SELECT anilist_id, anilist_name, site_url, channel_id, added_by FROM anilist WHERE channel_id = $1 AND ($2::TEXT IS NULL OR anilist_name ILIKE '%' || $2 || '%') ORDER BY anilist_id LIMIT $3 OFFSET $4",
+        &[&channel_id, &name, &limit, &options.offset],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let total: i64 = query_one(
+        &con,
+        "SELECT COUNT(*) FROM anilist WHERE channel_id = $1 AND ($2::TEXT IS NULL OR anilist_name ILIKE '%' || $2 || '%')",
+        &[&channel_id, &name],
+    )
+    .await
+    .map_err(internal_error)?
+    .get(0);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(X_TOTAL_COUNT, HeaderValue::from(total));
+
+    let users = result.iter().map(UserData::from).collect();
+
+    Ok((headers, Json(users)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/{channelid}/anilist/count",
+    tag = "anilist",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    responses((status = 200, description = "Number of AniList users subscribed to the channel", body = SubscriptionCount))
+)]
+pub async fn count_by_channel(
+    State(Replica(pool)): State<Replica>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+) -> Result<Json<SubscriptionCount>, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query_one(
+        &con,
+        "SELECT COUNT(*) FROM anilist WHERE channel_id = $1",
+        &[&channel_id],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok(Json(SubscriptionCount {
+        channel_id,
+        count: result.get(0),
+    }))
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{anilist, tests, AppState};
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::{delete, get, post},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use rand::{random, thread_rng, Rng};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    async fn init() -> (Router, Arc<Pool>) {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+
+        let arc_pool = Arc::new(pool);
+        let app = Router::new()
+            .route("/channel/:channelid/anilist", get(anilist::list_by_channel))
+            .route(
+                "/channel/:channelid/anilist/count",
+                get(anilist::count_by_channel),
+            )
+            .route("/anilist", post(anilist::add_user))
+            .route("/anilist", delete(anilist::remove_user))
+            .with_state(AppState::from(Arc::clone(&arc_pool)));
+
+        (app, arc_pool)
+    }
+
+    async fn insert_channel(pool: &Pool, channel_id: ChannelId) {
+        let con = pool.get().await.unwrap();
+        con.execute(
+            "INSERT INTO channels VALUES ($1, 'channel', $2, 'guild', NOW(), $3, FALSE)",
+            &[&channel_id, &random::<i64>(), &random::<i64>()],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn insert_user(pool: &Pool, user: &UserData) {
+        let con = pool.get().await.unwrap();
+        con.execute(
+            "INSERT INTO anilist VALUES ($1, $2, $3, $4, NOW(), $5)",
+            &[
+                &user.anilist_id,
+                &user.anilist_name,
+                &user.site_url,
+                &user.channel_id,
+                &user.added_by,
+            ],
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn list_by_channel_test() {
+        let (app, pool) = init().await;
+        let channel_id = ChannelId(random());
+        insert_channel(&pool, channel_id).await;
+
+        let users = [
+            UserData {
+                anilist_id: 1,
+                anilist_name: "first".to_string(),
+                site_url: "https://anilist.co/user/1/".to_string(),
+                channel_id,
+                added_by: UserId(random()),
+            },
+            UserData {
+                anilist_id: 2,
+                anilist_name: "second".to_string(),
+                site_url: "https://anilist.co/user/2/".to_string(),
+                channel_id,
+                added_by: UserId(random()),
+            },
+        ];
+        for user in &users {
+            insert_user(&pool, user).await;
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/channel/{channel_id}/anilist"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let mut body: Value = serde_json::from_slice(&body).unwrap();
+        body.as_array_mut()
+            .unwrap()
+            .sort_by_key(|user| user["anilist_id"].as_i64());
+        assert_eq!(body, json!(users));
+    }
+
+    #[tokio::test]
+    async fn list_by_channel_empty() {
+        let (app, _pool) = init().await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/channel/{}/anilist", random::<i64>()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!([]));
+    }
+
+    async fn get_users(app: Router, uri: String) -> (HeaderMap, Value) {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers().clone();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (headers, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn list_by_channel_paging_test() {
+        let (app, pool) = init().await;
+        let channel_id = ChannelId(random());
+        insert_channel(&pool, channel_id).await;
+
+        for (anilist_id, anilist_name) in [(1, "alpha"), (2, "beta"), (3, "alphabet")] {
+            let user = UserData {
+                anilist_id,
+                anilist_name: anilist_name.to_string(),
+                site_url: format!("https://anilist.co/user/{anilist_id}/"),
+                channel_id,
+                added_by: UserId(random()),
+            };
+            insert_user(&pool, &user).await;
+        }
+
+        let ids = |body: &Value| -> Vec<i64> {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|user| user["anilist_id"].as_i64().unwrap())
+                .collect()
+        };
+        let uri = format!("/channel/{channel_id}/anilist?limit=2");
+        let (headers, body) = get_users(app.clone(), uri.clone()).await;
+        assert_eq!(ids(&body), [1, 2]);
+        assert_eq!(headers[X_TOTAL_COUNT], "3");
+
+        let (_, body) = get_users(app.clone(), format!("{uri}&offset=2")).await;
+        assert_eq!(ids(&body), [3]);
+
+        let uri = format!("/channel/{channel_id}/anilist?name=ALPHA");
+        let (headers, body) = get_users(app.clone(), uri).await;
+        assert_eq!(ids(&body), [1, 3]);
+        assert_eq!(headers[X_TOTAL_COUNT], "2");
+
+        // Out of range limits are clamped like GET /channel's.
+        for (limit, expected) in [(-1, vec![1]), (0, vec![1]), (i64::MAX, vec![1, 2, 3])] {
+            let uri = format!("/channel/{channel_id}/anilist?limit={limit}");
+            let (_, body) = get_users(app.clone(), uri).await;
+            assert_eq!(ids(&body), expected);
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/channel/{channel_id}/anilist?offset=-1"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn count_by_channel_test() {
+        let (app, pool) = init().await;
+        let channel_id = ChannelId(random());
+        insert_channel(&pool, channel_id).await;
+        for anilist_id in 1..=2 {
+            insert_user(
+                &pool,
+                &UserData {
+                    anilist_id,
+                    anilist_name: anilist_id.to_string(),
+                    site_url: format!("https://anilist.co/user/{anilist_id}/"),
+                    channel_id,
+                    added_by: UserId(random()),
+                },
+            )
+            .await;
+        }
+
+        for (channel_id, count) in [(channel_id, 2), (ChannelId(random()), 0)] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(format!("/channel/{channel_id}/anilist/count"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body, json!({"channel_id": channel_id, "count": count}));
+        }
+    }
+
+    #[test]
+    fn parse_site_url_test() {
+        assert_eq!(
+            parse_site_url("https://anilist.co/user/12345").unwrap(),
+            12345
+        );
+        assert_eq!(
+            parse_site_url("https://anilist.co/user/12345/").unwrap(),
+            12345
+        );
+    }
+
+    #[test]
+    fn parse_site_url_bad_host_test() {
+        let (status, _) = parse_site_url("https://anilist.example.com/user/12345/").unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(parse_site_url("https://anilist.co/anime/12345/").is_err());
+    }
+
+    #[test]
+    fn validate_site_url_test() {
+        assert!(validate_site_url("https://anilist.co/user/12345/").is_ok());
+        assert!(validate_site_url("https://www.anilist.co/user/Name").is_ok());
+    }
+
+    #[test]
+    fn validate_site_url_http_test() {
+        let (status, _) = validate_site_url("http://anilist.co/user/12345/").unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn validate_site_url_foreign_host_test() {
+        let (status, _) = validate_site_url("https://myanimelist.net/profile/12345").unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        // Userinfo doesn't make a foreign host look like AniList.
+        assert!(validate_site_url("https://anilist.co@evil.example.com/user/1").is_err());
+    }
+
+    async fn post_user(app: Router, body: Value) -> (StatusCode, Value) {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/anilist")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn add_user_by_url_test() {
+        let (app, pool) = init().await;
+        let channel_id = ChannelId(thread_rng().gen_range(1..=i64::MAX));
+        insert_channel(&pool, channel_id).await;
+
+        let body = json!({
+            "channel_id": channel_id,
+            "added_by": 7,
+            "type": "URL",
+            "value": "https://anilist.co/user/12345/"
+        });
+        let (status, body) = post_user(app.clone(), body).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["anilist_id"], 12345);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/channel/{channel_id}/anilist"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let stored = response.into_body().collect().await.unwrap().to_bytes();
+        let stored: Value = serde_json::from_slice(&stored).unwrap();
+        assert_eq!(
+            stored,
+            json!([{
+                "anilist_id": 12345,
+                "anilist_name": "12345",
+                "site_url": "https://anilist.co/user/12345/",
+                "channel_id": channel_id,
+                "added_by": 7
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn add_user_refresh_test() {
+        let (app, pool) = init().await;
+        let channel_id = ChannelId(thread_rng().gen_range(1..=i64::MAX));
+        insert_channel(&pool, channel_id).await;
+
+        let body = json!({
+            "channel_id": channel_id,
+            "added_by": 7,
+            "anilist_name": "old",
+            "type": "URL",
+            "value": "https://anilist.co/user/12345/"
+        });
+        let (status, _) = post_user(app.clone(), body).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let body = json!({
+            "channel_id": channel_id,
+            "added_by": 8,
+            "anilist_name": "new",
+            "type": "URL",
+            "value": "https://anilist.co/user/12345"
+        });
+        let (status, body) = post_user(app.clone(), body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({
+                "anilist_id": 12345,
+                "anilist_name": "new",
+                "site_url": "https://anilist.co/user/12345",
+                "channel_id": channel_id,
+                "added_by": 7
+            })
+        );
+
+        let (_, users) = get_users(app, format!("/channel/{channel_id}/anilist")).await;
+        assert_eq!(users.as_array().unwrap().len(), 1);
+        assert_eq!(users[0]["anilist_name"], "new");
+    }
+
+    async fn delete_user(app: Router, body: Value) -> StatusCode {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/anilist")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        response.status()
+    }
+
+    #[tokio::test]
+    async fn remove_user_test() {
+        let (app, pool) = init().await;
+        let channel_id = ChannelId(thread_rng().gen_range(1..=i64::MAX));
+        insert_channel(&pool, channel_id).await;
+        for (id, name) in [(1, "first"), (2, "Second")] {
+            let body = json!({
+                "channel_id": channel_id,
+                "added_by": 7,
+                "anilist_name": name,
+                "type": "URL",
+                "value": format!("https://anilist.co/user/{id}/")
+            });
+            let (status, _) = post_user(app.clone(), body).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+
+        let by_url = json!({
+            "channel_id": channel_id,
+            "type": "URL",
+            "value": "https://anilist.co/user/1"
+        });
+        assert_eq!(
+            delete_user(app.clone(), by_url.clone()).await,
+            StatusCode::NO_CONTENT
+        );
+        // Already gone, so nothing was removed this time.
+        assert_eq!(
+            delete_user(app.clone(), by_url).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let by_name = json!({ "channel_id": channel_id, "type": "NAME", "value": "second" });
+        assert_eq!(
+            delete_user(app.clone(), by_name).await,
+            StatusCode::NO_CONTENT
+        );
+
+        let (_, users) = get_users(app, format!("/channel/{channel_id}/anilist")).await;
+        assert_eq!(users, json!([]));
+    }
+
+    #[tokio::test]
+    async fn add_user_bad_url_test() {
+        let (app, _pool) = init().await;
+
+        let body = json!({
+            "channel_id": 1,
+            "added_by": 7,
+            "type": "URL",
+            "value": "https://myanimelist.net/profile/12345"
+        });
+        let (status, body) = post_user(app, body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_error");
+    }
+}
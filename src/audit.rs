@@ -0,0 +1,382 @@
+// CREATE TABLE audit_log (
+//     id BIGSERIAL PRIMARY KEY,
+//     ts TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+//     method TEXT NOT NULL,
+//     path TEXT NOT NULL,
+//     actor TEXT NOT NULL,
+//     affected_id BIGINT,
+//     sqlstate TEXT NOT NULL
+//   )
+//
+// Append-only: a trigger rejects UPDATE and DELETE on it.
+
+use axum::{
+    extract::{MatchedPath, Query, RawPathParams, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{GenericClient, Pool};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    util::{
+        auth::Actor,
+        db::{execute, query, with_retry, Replica},
+        error_handling::{internal_error, pool_error},
+    },
+    Message,
+};
+
+// Recorded when the request got through without an API key, which only
+// happens where the auth layer isn't installed, e.g. in tests.
+const ANONYMOUS: &str = "anonymous";
+
+// SQLSTATE successful_completion, recorded for writes that went through.
+const SUCCESSFUL_COMPLETION: &str = "00000";
+
+// Reads that take their IDs in a POST body, never logged.
+const READ_ONLY_POSTS: [&str; 2] = ["/channel/batch-get", "/guild/summaries"];
+
+tokio::task_local! {
+    static SQLSTATE: RefCell<Option<String>>;
+}
+
+// Set by a handler on a successful response to a write that changed nothing,
+// e.g. a ?dry_run=true delete, so it isn't logged.
+#[derive(Clone, Copy)]
+pub struct Unchanged;
+
+#[derive(Deserialize, IntoParams)]
+pub struct AuditOptions {
+    /// Clamped to 1..=100
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    id: i64,
+    ts: DateTime<Utc>,
+    method: String,
+    path: String,
+    actor: String,
+    affected_id: Option<i64>,
+    /// 00000 for a write that went through, otherwise why Postgres refused it
+    sqlstate: String,
+}
+
+// Called by internal_error, so a write Postgres refused is logged with the
+// reason. Does nothing outside of `record`.
+pub(crate) fn note_sqlstate(code: &str) {
+    let _ = SQLSTATE.try_with(|sqlstate| *sqlstate.borrow_mut() = Some(code.to_string()));
+}
+
+// Writes a row after every POST, PUT, PATCH and DELETE that either succeeded
+// without being marked Unchanged or was refused by Postgres. The handler has
+// already committed by then, so a failed insert is logged rather than turned
+// into an error for a write that did happen.
+pub async fn record(
+    State(pool): State<Arc<Pool>>,
+    params: Option<RawPathParams>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    ) {
+        return next.run(request).await;
+    }
+    let read_only = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| READ_ONLY_POSTS.contains(&path.as_str()));
+    if read_only {
+        return next.run(request).await;
+    }
+
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let actor = request
+        .extensions()
+        .get::<Actor>()
+        .map_or(ANONYMOUS, |actor| actor.0);
+    let path_id = params.and_then(|params| {
+        params
+            .iter()
+            .find_map(|(_, value)| value.parse::<i64>().ok())
+    });
+
+    let (response, refused) = SQLSTATE
+        .scope(RefCell::new(None), async {
+            let response = next.run(request).await;
+            (response, SQLSTATE.with(RefCell::take))
+        })
+        .await;
+    let sqlstate = if response.status().is_success() {
+        if response.extensions().get::<Unchanged>().is_some() {
+            return response;
+        }
+        SUCCESSFUL_COMPLETION.to_string()
+    } else {
+        match refused {
+            Some(sqlstate) => sqlstate,
+            None => return response,
+        }
+    };
+
+    // POST /channel and friends only name the new row in Location.
+    let affected_id = path_id.or_else(|| {
+        response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|location| location.split('/').find_map(|segment| segment.parse().ok()))
+    });
+
+    let inserted = async {
+        let con = with_retry(|| pool.get()).await?;
+        execute(
+            &con,
+            "INSERT INTO audit_log (method, path, actor, affected_id, sqlstate) VALUES ($1, $2, $3, $4, $5)",
+            &[&method, &path, &actor, &affected_id, &sqlstate],
+        )
+        .await?;
+
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+    };
+    if let Err(err) = inserted.await {
+        tracing::error!(%method, %path, "Couldn't write audit log entry: {err}");
+    }
+
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditOptions),
+    responses(
+        (status = 200, description = "Most recent writes first", body = Vec<AuditEntry>),
+        (status = 401, description = "Missing or invalid API key", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list(
+    State(Replica(pool)): State<Replica>,
+    Query(options): Query<AuditOptions>,
+) -> Result<Json<Vec<AuditEntry>>, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let entries = recent(&con, None, &options).await.map_err(internal_error)?;
+
+    Ok(Json(entries))
+}
+
+// The newest entries, only those that touched `affected_id` when given.
+pub(crate) async fn recent(
+    client: &impl GenericClient,
+    affected_id: Option<i64>,
+    options: &AuditOptions,
+) -> Result<Vec<AuditEntry>, tokio_postgres::Error> {
+    let limit = options.limit.clamp(1, MAX_LIMIT);
+
+    let result = query(
+        client,
+        "SELECT id, ts, method, path, actor, affected_id, sqlstate FROM audit_log WHERE $1::BIGINT IS NULL OR affected_id = $1 ORDER BY id DESC LIMIT $2",
+        &[&affected_id, &limit],
+    )
+    .await?;
+
+    let entries = result
+        .iter()
+        .map(|row| AuditEntry {
+            id: row.get("id"),
+            ts: row.get("ts"),
+            method: row.get("method"),
+            path: row.get("path"),
+            actor: row.get("actor"),
+            affected_id: row.get("affected_id"),
+            sqlstate: row.get("sqlstate"),
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{audit, channel, channel::DuplicatePolicy, guild, tests, AppState};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        middleware,
+        routing::{get, post},
+        Router,
+    };
+    use deadpool_postgres::Pool;
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn init(pool: &Arc<Pool>, duplicate_policy: DuplicatePolicy) -> Router {
+        Router::new()
+            .route("/channel", post(channel::add))
+            .route("/channel/:channelid", get(channel::get))
+            .route("/channel/batch-get", post(channel::get_many))
+            .route("/channel/bulk-delete", post(channel::delete_bulk))
+            .route("/guild/summaries", post(guild::summaries))
+            .route("/audit", get(audit::list))
+            .route_layer(middleware::from_fn_with_state(
+                Arc::clone(pool),
+                audit::record,
+            ))
+            .with_state(AppState {
+                duplicate_policy,
+                ..AppState::from(Arc::clone(pool))
+            })
+    }
+
+    async fn post_json(app: &Router, uri: &str, body: &Value) -> StatusCode {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        response.status()
+    }
+
+    async fn entries(app: &Router) -> Vec<Value> {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/audit?limit=10")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        body.as_array().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn record_test() {
+        let pool = Arc::new(tests::pool());
+        tests::reset(&pool).await;
+        let app = init(&pool, DuplicatePolicy::Conflict);
+
+        let channel_id = tests::snowflake();
+        let body = json!({
+            "channel_id": channel_id,
+            "channel_name": "channel",
+            "guild_id": tests::snowflake(),
+            "added_by": tests::snowflake(),
+        });
+        assert_eq!(
+            post_json(&app, "/channel", &body).await,
+            StatusCode::CREATED
+        );
+
+        // Neither are dry runs, or reads that happen to be POSTs.
+        let status = post_json(
+            &app,
+            "/channel/bulk-delete?dry_run=true",
+            &json!([channel_id]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let status = post_json(&app, "/channel/batch-get", &json!([channel_id])).await;
+        assert_eq!(status, StatusCode::OK);
+        let status = post_json(&app, "/guild/summaries", &json!([tests::snowflake()])).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Reads aren't logged.
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/channel/{channel_id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A write Postgres refused is logged with its SQLSTATE.
+        assert_eq!(
+            post_json(&app, "/channel", &body).await,
+            StatusCode::CONFLICT
+        );
+
+        let entries = entries(&app).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["path"], "/channel");
+        assert_eq!(entries[0]["sqlstate"], "23505");
+        assert_eq!(entries[1]["method"], "POST");
+        assert_eq!(entries[1]["path"], "/channel");
+        assert_eq!(entries[1]["actor"], "anonymous");
+        assert_eq!(entries[1]["affected_id"], channel_id);
+        assert_eq!(entries[1]["sqlstate"], "00000");
+
+        // Entries can't be edited or removed after the fact.
+        let con = pool.get().await.unwrap();
+        assert!(con
+            .execute("UPDATE audit_log SET actor = 'someone'", &[])
+            .await
+            .is_err());
+        assert!(con.execute("DELETE FROM audit_log", &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn record_duplicate_ignored_test() {
+        let pool = Arc::new(tests::pool());
+        tests::reset(&pool).await;
+        let app = init(&pool, DuplicatePolicy::Ignore);
+
+        let body = json!({
+            "channel_id": tests::snowflake(),
+            "channel_name": "channel",
+            "guild_id": tests::snowflake(),
+            "added_by": tests::snowflake(),
+        });
+        assert_eq!(
+            post_json(&app, "/channel", &body).await,
+            StatusCode::CREATED
+        );
+        assert_eq!(post_json(&app, "/channel", &body).await, StatusCode::OK);
+
+        assert_eq!(entries(&app).await.len(), 1);
+    }
+}
@@ -5,282 +5,4520 @@
 //     guild_name TEXT NOT NULL,
 //     added_at TIMESTAMPTZ NOT NULL,
 //     added_by BIGINT NOT NULL,
-//     suppress BOOLEAN NOT NULL
+//     suppress BOOLEAN NOT NULL,
+//     deleted_at TIMESTAMPTZ,
+//     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+//     last_seen_at TIMESTAMPTZ
 //   )
 
-use crate::{util::error_handling::internal_error, Message};
+use crate::{
+    anilist::{self, UserData},
+    audit,
+    types::{self, ChannelId, GuildId, UserId},
+    util::{
+        accept,
+        db::{
+            execute, query, query_one, query_opt, query_raw, with_retry, with_transaction, Replica,
+        },
+        error_handling::{internal_error, pool_error, server_error},
+        etag,
+        events::{self, Operation},
+        i18n,
+        json::ValidatedJson,
+        path::ValidatedPath,
+        validation::{validate_snowflake, validate_snowflake_epoch, FieldErrors},
+    },
+    Message,
+};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
+    body::Body,
+    extract::{FromRequest, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    BoxError, Json,
 };
-use deadpool_postgres::{GenericClient, Pool};
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio_postgres::error::DbError;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{str::FromStr, sync::Arc};
+use tokio_postgres::{types::ToSql, Row};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use utoipa::{IntoParams, ToSchema};
+
+// Discord doesn't always tell the bot a guild's name, so a missing or empty
+// guild_name is stored as UNKNOWN_GUILD_NAME rather than rejected.
+const UNKNOWN_GUILD_NAME: &str = "Unknown";
+
+// Columns of `channels` backing `Data`, in field order. A macro rather than
+// a const so it can be spliced into other SQL literals with concat!.
+macro_rules! channel_columns {
+    () => {
+        "channel_id, channel_name, guild_id, guild_name, suppress, added_at, added_by, last_seen_at"
+    };
+}
+
+// Column order of the parameters every channel INSERT binds: $1 channel_id,
+// $2 channel_name, $3 guild_id, $4 guild_name, $5 added_by, $6 suppress.
+// added_at is whatever SQL the caller passes, usually NOW().
+macro_rules! insert_columns {
+    ($added_at:literal) => {
+        concat!(
+            "INSERT INTO channels (channel_id, channel_name, guild_id, guild_name, added_at, added_by, suppress) VALUES ($1, $2, $3, $4, ",
+            $added_at,
+            ", $5, "
+        )
+    };
+}
+
+// $7 is the optional added_at of an import.
+macro_rules! insert_channel {
+    ($on_conflict:literal) => {
+        concat!(
+            insert_columns!("COALESCE($7, NOW())"),
+            "COALESCE($6, (SELECT suppress FROM guild_defaults WHERE guild_id = $3), FALSE)) ",
+            $on_conflict,
+            "RETURNING updated_at"
+        )
+    };
+}
+
+const INSERT_CHANNEL: &str = insert_channel!("");
+
+// Returns no row when the channel already exists.
+const INSERT_CHANNEL_OR_IGNORE: &str = insert_channel!("ON CONFLICT (channel_id) DO NOTHING ");
+
+// How add answers a channel_id that's already registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    #[default]
+    Conflict,
+    // Answer 200 and leave the stored channel as it is, for clients that
+    // retry a POST without knowing whether the first one landed.
+    Ignore,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "conflict" => Ok(DuplicatePolicy::Conflict),
+            "ignore" => Ok(DuplicatePolicy::Ignore),
+            _ => Err(format!(
+                "unknown duplicate policy {value:?}, expected conflict or ignore"
+            )),
+        }
+    }
+}
 
-#[derive(Serialize, Deserialize)]
+pub const X_TOTAL_COUNT: &str = "x-total-count";
+pub const X_NEXT_CURSOR: &str = "x-next-cursor";
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Create {
-    channel_id: i64,
+    channel_id: ChannelId,
     channel_name: String,
-    guild_id: i64,
-    guild_name: String,
-    added_by: i64,
+    guild_id: GuildId,
+    /// Defaults to "Unknown" when missing or empty
+    guild_name: Option<String>,
+    added_by: UserId,
+    /// Defaults to the guild's default, see PUT /guild/{guildid}/defaults
+    suppress: Option<bool>,
+    /// Original registration time when importing, defaults to now. Only
+    /// accepted with ENABLE_ADMIN_ENDPOINTS=true
+    added_at: Option<DateTime<Utc>>,
+}
+
+// Full channel representation for PUT, the ID comes from the path.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Replace {
+    channel_name: String,
+    guild_id: GuildId,
+    /// Defaults to "Unknown" when missing or empty
+    guild_name: Option<String>,
+    added_by: UserId,
     suppress: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Data {
     #[serde(skip_serializing_if = "Option::is_none")]
-    channel_id: Option<i64>,
+    channel_id: Option<ChannelId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     channel_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    guild_id: Option<i64>,
+    guild_id: Option<GuildId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     guild_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     suppress: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    added_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    added_by: Option<UserId>,
+    /// When the bot last saw activity in the channel, see POST /channel/{channelid}/touch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_seen_at: Option<DateTime<Utc>>,
+}
+
+impl From<&Row> for Data {
+    fn from(row: &Row) -> Self {
+        Data {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+            guild_id: row.get("guild_id"),
+            guild_name: row.get("guild_name"),
+            suppress: row.get("suppress"),
+            added_at: row.get("added_at"),
+            added_by: row.get("added_by"),
+            last_seen_at: row.get("last_seen_at"),
+        }
+    }
+}
+
+// Body of the writes to a single channel, so clients can keep their cached
+// copy in step with the server's updated_at.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Mutation {
+    /// Only set by the suppress toggle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppress: Option<bool>,
+    updated_at: DateTime<Utc>,
+}
+
+// A channel together with its initial AniList subscriptions, each of which
+// is subscribed to `channel` whatever its own channel_id says.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Registration {
+    channel: Create,
+    anilist: Vec<UserData>,
+}
+
+// What GET /channel/{channelid}/full returns, the channel next to its
+// subscriptions.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ChannelDetail {
+    channel: Data,
+    anilist: Vec<UserData>,
+}
+
+// What GET /channel/{channelid}/detail returns. channel is null once the
+// channel is deleted, its history stays.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ChannelHistory {
+    channel: Option<Data>,
+    history: Vec<audit::AuditEntry>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GuildSuppress {
+    suppress: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SuppressMany {
+    channel_ids: Vec<ChannelId>,
+    suppress: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Owner {
+    added_by: UserId,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ListOptions {
+    /// Only channels added at or after this RFC 3339 timestamp
+    from: Option<String>,
+    /// Only channels added at or before this RFC 3339 timestamp, defaults to now when from is set
+    to: Option<String>,
+    /// Clamped to 1..=1000
+    #[serde(default = "default_list_limit")]
+    limit: i64,
+    /// Must not be negative
+    #[serde(default)]
+    offset: i64,
+    /// Opaque next_cursor from the previous page
+    cursor: Option<String>,
+    /// Only channels with this suppress value
+    suppress: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ChannelPage {
+    items: Vec<Data>,
+    /// Pass as cursor to fetch the next page, absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+fn default_list_limit() -> i64 {
+    50
+}
+
+pub(crate) const LIST_MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize, IntoParams)]
+pub struct SearchOptions {
+    /// Substring of the channel name, matched case-insensitively
+    #[serde(default)]
+    q: String,
+    /// Clamped to 1..=100
+    #[serde(default = "default_search_limit")]
+    limit: i64,
 }
 
+fn default_search_limit() -> i64 {
+    20
+}
+
+const SEARCH_MAX_LIMIT: i64 = 100;
+
+#[derive(Deserialize, IntoParams)]
+pub struct AutocompleteOptions {
+    /// Prefix of the channel name, matched case-insensitively
+    #[serde(default)]
+    q: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RecentOptions {
+    /// Clamped to 1..=100
+    #[serde(default = "default_recent_limit")]
+    limit: i64,
+}
+
+fn default_recent_limit() -> i64 {
+    20
+}
+
+const RECENT_MAX_LIMIT: i64 = 100;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RecentChannel {
+    channel_id: ChannelId,
+    channel_name: String,
+    guild_name: String,
+    added_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct TopOptions {
+    /// Clamped to 1..=100
+    #[serde(default = "default_top_limit")]
+    limit: i64,
+    /// Leave out channels with fewer subscriptions than this
+    #[serde(default)]
+    min: i64,
+}
+
+fn default_top_limit() -> i64 {
+    10
+}
+
+const TOP_MAX_LIMIT: i64 = 100;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TopChannel {
+    channel_id: ChannelId,
+    channel_name: String,
+    subscription_count: i64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ChannelName {
+    channel_id: ChannelId,
+    channel_name: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DeleteOptions {
+    #[serde(default)]
+    hard: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct GetOptions {
+    /// Comma-separated subset of the fields to return, e.g. suppress,channel_name
+    fields: Option<String>,
+}
+
+// What get returns, and so what fields may ask for. Doubles as the set of
+// column names allowed into the projected SELECT.
+const GET_FIELDS: [&str; 6] = [
+    "channel_name",
+    "guild_id",
+    "guild_name",
+    "suppress",
+    "added_by",
+    "last_seen_at",
+];
+
+#[derive(Deserialize, IntoParams)]
+pub struct IdempotentOptions {
+    /// Answer 204 whether or not the channel existed, so retries always succeed
+    #[serde(default)]
+    idempotent: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DryRunOptions {
+    /// Count the rows that would be deleted without deleting them
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ConfirmOptions {
+    /// Must repeat the guild ID from the path, unless dry_run is set
+    confirm: Option<String>,
+}
+
+pub(crate) fn guild_name_or_unknown(guild_name: Option<String>) -> String {
+    guild_name
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| UNKNOWN_GUILD_NAME.to_string())
+}
+
+const MERGE_PATCH: &str = "application/merge-patch+json";
+
+// The two columns PATCH can change. Both content types deserialize into it,
+// so anything else in the body is rejected rather than silently dropped.
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelPatch {
+    /// null leaves it alone, or resets it to false in a merge patch
+    #[serde(default, deserialize_with = "null_as_some")]
+    #[schema(value_type = Option<bool>)]
+    suppress: Option<Option<bool>>,
+    /// An empty string or null clears it to "Unknown"
+    #[serde(default, deserialize_with = "null_as_empty")]
+    guild_name: Option<String>,
+}
+
+fn is_merge_patch(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|essence| essence.trim().eq_ignore_ascii_case(MERGE_PATCH))
+}
+
+// Keeps an explicit null apart from a missing field, which `default` leaves
+// as None.
+fn null_as_some<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// Lets PATCH tell an explicit null, read as "", apart from a missing field,
+// which `default` leaves as None.
+fn null_as_empty<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(|value| Some(value.unwrap_or_default()))
+}
+
+// Marked unchanged so the audit log doesn't record a delete that didn't
+// happen.
+fn dry_run_message(count: i64) -> Response {
+    let msg = Message {
+        message: format!("dry run: {count} rows would be deleted"),
+        data: Some(vec![count.to_string()]),
+        ..Default::default()
+    };
+
+    let mut response = Json(msg).into_response();
+    response.extensions_mut().insert(audit::Unchanged);
+    response
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel",
+    tag = "channels",
+    request_body = Create,
+    responses(
+        (status = 201, description = "Channel created", body = Mutation, headers(("Location" = String, description = "URL of the new channel"))),
+        (status = 200, description = "Channel already exists and DUPLICATE_POLICY=ignore, nothing was changed", body = Message),
+        (status = 400, description = "Malformed body, or an ID that can't be a Discord snowflake", body = Message),
+        (status = 409, description = "Channel already exists", body = Message),
+        (status = 422, description = "Invalid fields, one per entry in data", body = Message),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn add(
     State(pool): State<Arc<Pool>>,
-    Json(payload): Json<Create>,
-) -> Result<StatusCode, (StatusCode, Json<Message>)> {
+    State(policy): State<DuplicatePolicy>,
+    ValidatedJson(payload): ValidatedJson<Create>,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .snowflake("channel_id", payload.channel_id)
+        .not_empty("channel_name", &payload.channel_name)
+        .snowflake("guild_id", payload.guild_id)
+        .snowflake("added_by", payload.added_by)
+        .admin_only("added_at", payload.added_at.is_some())
+        .finish()?;
+    validate_snowflake_epoch("channel_id", payload.channel_id)?;
+    validate_snowflake_epoch("guild_id", payload.guild_id)?;
+    validate_snowflake_epoch("added_by", payload.added_by)?;
+
     let pool = Arc::clone(&pool);
-    let con = pool
-        .get()
-        .await
-        .map_err(|err| internal_error(Box::new(err)))?;
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
 
-    let statement = con
-        .prepare("INSERT INTO channels VALUES ($1, $2, $3, $4, NOW(), $5, $6)")
-        .await
-        .map_err(|err| {
-            let db_error = DbError::clone(err.as_db_error().unwrap());
-            internal_error(Box::new(db_error))
-        })?;
+    let sql = match policy {
+        DuplicatePolicy::Conflict => INSERT_CHANNEL,
+        DuplicatePolicy::Ignore => INSERT_CHANNEL_OR_IGNORE,
+    };
+    let result = query_opt(
+        &con,
+        sql,
+        &[
+            &payload.channel_id,
+            &payload.channel_name,
+            &payload.guild_id,
+            &guild_name_or_unknown(payload.guild_name),
+            &payload.added_by,
+            &payload.suppress,
+            &payload.added_at,
+        ],
+    )
+    .await
+    .map_err(internal_error)?;
+    let Some(result) = result else {
+        let msg = Message {
+            message: format!("{} already exists", payload.channel_id),
+            ..Default::default()
+        };
 
-    let _result = con
-        .execute(
-            &statement,
-            &[
-                &payload.channel_id,
-                &payload.channel_name,
-                &payload.guild_id,
-                &payload.guild_name,
-                &payload.added_by,
-                &payload.suppress.unwrap_or_default(),
-            ],
-        )
-        .await
-        .map_err(|err| {
-            let db_error = DbError::clone(err.as_db_error().unwrap());
-            internal_error(Box::new(db_error))
-        })?;
+        // Nothing was written, so it isn't logged.
+        let mut response = (StatusCode::OK, Json(msg)).into_response();
+        response.extensions_mut().insert(audit::Unchanged);
+        return Ok(response);
+    };
+    events::publish(Operation::Create, payload.channel_id);
+
+    let mut headers = HeaderMap::new();
+    let location = format!("/channel/{}", payload.channel_id);
+    headers.insert(
+        header::LOCATION,
+        HeaderValue::from_str(&location).expect("channel URL is a valid header value"),
+    );
+
+    let mutation = Mutation {
+        suppress: None,
+        updated_at: result.get("updated_at"),
+    };
 
-    Ok(StatusCode::CREATED)
+    Ok((StatusCode::CREATED, headers, Json(mutation)).into_response())
 }
 
-pub async fn get(
+#[utoipa::path(
+    post,
+    path = "/channel/full",
+    tag = "channels",
+    request_body = Registration,
+    responses(
+        (status = 201, description = "Channel and subscriptions created", body = Message, headers(("Location" = String, description = "URL of the new channel"))),
+        (status = 409, description = "Channel or a subscription already exists, nothing was created", body = Message),
+        (status = 422, description = "Invalid fields, one per entry in data", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn register_with_subscriptions(
     State(pool): State<Arc<Pool>>,
-    Path(channel_id): Path<i64>,
-) -> Result<Json<Data>, (StatusCode, Json<Message>)> {
+    ValidatedJson(payload): ValidatedJson<Registration>,
+) -> Result<(StatusCode, HeaderMap, Json<Message>), (StatusCode, Json<Message>)> {
+    let Registration {
+        channel,
+        anilist: users,
+    } = payload;
+    FieldErrors::default()
+        .max_items("anilist", users.len())
+        .snowflake("channel_id", channel.channel_id)
+        .not_empty("channel_name", &channel.channel_name)
+        .snowflake("guild_id", channel.guild_id)
+        .snowflake("added_by", channel.added_by)
+        .admin_only("added_at", channel.added_at.is_some())
+        .site_urls("anilist", users.iter().map(|user| user.site_url.as_str()))
+        .finish()?;
+    validate_snowflake_epoch("channel_id", channel.channel_id)?;
+    validate_snowflake_epoch("guild_id", channel.guild_id)?;
+    validate_snowflake_epoch("added_by", channel.added_by)?;
+
+    let channel_id = channel.channel_id;
+    let subscriptions = with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            execute(
+                tx,
+                INSERT_CHANNEL,
+                &[
+                    &channel.channel_id,
+                    &channel.channel_name,
+                    &channel.guild_id,
+                    &guild_name_or_unknown(channel.guild_name),
+                    &channel.added_by,
+                    &channel.suppress,
+                    &channel.added_at,
+                ],
+            )
+            .await
+            .map_err(internal_error)?;
+
+            for user in &users {
+                anilist::insert_user(tx, user, channel_id).await?;
+            }
+
+            Ok(users.len())
+        })
+    })
+    .await?;
+    events::publish(Operation::Create, channel_id);
+
+    let mut headers = HeaderMap::new();
+    let location = format!("/channel/{channel_id}");
+    headers.insert(
+        header::LOCATION,
+        HeaderValue::from_str(&location).expect("channel URL is a valid header value"),
+    );
+
+    let msg = Message {
+        message: format!("Registered {channel_id} with {subscriptions} subscriptions"),
+        data: Some(vec![subscriptions.to_string()]),
+        ..Default::default()
+    };
+
+    Ok((StatusCode::CREATED, headers, Json(msg)))
+}
+
+// Inserts the channels it hasn't seen and updates the ones whose fields
+// differ, restoring soft-deleted ones. A missing suppress keeps the stored
+// value, or falls back to the guild default for new channels.
+#[utoipa::path(
+    post,
+    path = "/channel/sync",
+    tag = "channels",
+    request_body = Vec<Create>,
+    responses(
+        (status = 200, description = "Numbers of inserted and updated channels, in data", body = Message),
+        (status = 409, description = "A channel_id appears twice, nothing was written", body = Message),
+        (status = 422, description = "Invalid fields, one per entry in data", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn sync(
+    State(pool): State<Arc<Pool>>,
+    ValidatedJson(payload): ValidatedJson<Vec<Create>>,
+) -> Result<Json<Message>, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .max_items("channels", payload.len())
+        .finish()?;
+
+    let mut errors = FieldErrors::default();
+    for channel in &payload {
+        errors = errors
+            .snowflake("channel_id", channel.channel_id)
+            .not_empty("channel_name", &channel.channel_name)
+            .snowflake("guild_id", channel.guild_id)
+            .snowflake("added_by", channel.added_by)
+            .admin_only("added_at", channel.added_at.is_some());
+    }
+    errors.finish()?;
+    for channel in &payload {
+        validate_snowflake_epoch("channel_id", channel.channel_id)?;
+        validate_snowflake_epoch("guild_id", channel.guild_id)?;
+        validate_snowflake_epoch("added_by", channel.added_by)?;
+    }
+
+    let channel_ids: Vec<ChannelId> = payload.iter().map(|channel| channel.channel_id).collect();
+    let channel_names: Vec<String> = payload
+        .iter()
+        .map(|channel| channel.channel_name.clone())
+        .collect();
+    let guild_ids: Vec<GuildId> = payload.iter().map(|channel| channel.guild_id).collect();
+    let guild_names: Vec<String> = payload
+        .iter()
+        .map(|channel| guild_name_or_unknown(channel.guild_name.clone()))
+        .collect();
+    let added_by: Vec<UserId> = payload.iter().map(|channel| channel.added_by).collect();
+    let suppress: Vec<Option<bool>> = payload.iter().map(|channel| channel.suppress).collect();
+    let added_at: Vec<Option<DateTime<Utc>>> =
+        payload.iter().map(|channel| channel.added_at).collect();
+
+    let rows = with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            // One statement for the whole batch. xmax is only zero for a
+            // freshly inserted row, and rows that didn't change are skipped
+            // by the WHERE so they're not returned at all. added_at is only
+            // used for new rows.
+            query(
+                tx,
+                "WITH input AS (SELECT * FROM UNNEST($1::BIGINT[], $2::TEXT[], $3::BIGINT[], $4::TEXT[], $5::BIGINT[], $6::BOOLEAN[], $7::TIMESTAMPTZ[]) AS i (channel_id, channel_name, guild_id, guild_name, added_by, suppress, added_at)) \
+                 INSERT INTO channels (channel_id, channel_name, guild_id, guild_name, added_at, added_by, suppress) \
+                 SELECT i.channel_id, i.channel_name, i.guild_id, i.guild_name, COALESCE(i.added_at, NOW()), i.added_by, COALESCE(i.suppress, c.suppress, d.suppress, FALSE) \
+                 FROM input i LEFT JOIN channels c ON c.channel_id = i.channel_id LEFT JOIN guild_defaults d ON d.guild_id = i.guild_id \
+                 ON CONFLICT (channel_id) DO UPDATE SET channel_name = EXCLUDED.channel_name, guild_id = EXCLUDED.guild_id, guild_name = EXCLUDED.guild_name, added_by = EXCLUDED.added_by, suppress = EXCLUDED.suppress, deleted_at = NULL, updated_at = NOW() \
+                 WHERE (channels.channel_name, channels.guild_id, channels.guild_name, channels.added_by, channels.suppress, channels.deleted_at IS NULL) \
+                 IS DISTINCT FROM (EXCLUDED.channel_name, EXCLUDED.guild_id, EXCLUDED.guild_name, EXCLUDED.added_by, EXCLUDED.suppress, TRUE) \
+                 RETURNING channel_id, (xmax = 0) AS inserted",
+                &[&channel_ids, &channel_names, &guild_ids, &guild_names, &added_by, &suppress, &added_at],
+            )
+            .await
+            .map_err(internal_error)
+        })
+    })
+    .await?;
+
+    let mut inserted = 0;
+    for row in &rows {
+        let operation = if row.get("inserted") {
+            inserted += 1;
+            Operation::Create
+        } else {
+            Operation::Update
+        };
+        events::publish(operation, row.get("channel_id"));
+    }
+    let updated = rows.len() - inserted;
+
+    let msg = Message {
+        message: format!(
+            "Synced {} channels: {inserted} inserted, {updated} updated",
+            payload.len()
+        ),
+        data: Some(vec![inserted.to_string(), updated.to_string()]),
+        ..Default::default()
+    };
+
+    Ok(Json(msg))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/{channelid}",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID"), GetOptions),
+    responses(
+        (status = 200, description = "Channel found", body = Data, headers(("ETag" = String), ("Last-Modified" = String))),
+        (status = 304, description = "Channel unchanged since If-None-Match"),
+        (status = 400, description = "Unknown name in fields", body = Message),
+        (status = 404, description = "Channel not found", body = Message),
+    )
+)]
+pub async fn get(
+    State(Replica(pool)): State<Replica>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+    Query(options): Query<GetOptions>,
+    method: Method,
+    request_headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    if method == Method::HEAD {
+        let status = exists(State(Replica(pool)), ValidatedPath(channel_id)).await?;
+        return Ok(status.into_response());
+    }
+    if let Some(fields) = options.fields.as_deref() {
+        let fields = parse_fields(fields)?;
+        return get_fields(&pool, channel_id, &fields, &request_headers).await;
+    }
+
     let pool = Arc::clone(&pool);
-    let con = pool
-        .get()
-        .await
-        .map_err(|err| internal_error(Box::new(err)))?;
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
 
-    let statement = con
-        .prepare("SELECT channel_name, guild_id, guild_name, suppress FROM channels WHERE channel_id = $1")
-        .await
-        .map_err(|err| {
-            let db_error = DbError::clone(err.as_db_error().unwrap());
-            internal_error(Box::new(db_error))
-        })?;
+    let result = query_one(
+        &con,
+        concat!(
+            "SELECT ",
+            channel_columns!(),
+            ", updated_at FROM channels WHERE channel_id = $1 AND deleted_at IS NULL"
+        ),
+        &[&channel_id],
+    )
+    .await
+    .map_err(|_| {
+        let msg = Message {
+            message: i18n::not_found(channel_id),
+            code: Some("not_found".to_string()),
+            ..Default::default()
+        };
 
-    let result = con
-        .query_one(&statement, &[&channel_id])
-        .await
-        .map_err(|_| {
-            let msg = Message {
-                message: format!("Could not find {channel_id}"),
-                ..Default::default()
-            };
-            
-            (StatusCode::NOT_FOUND, Json(msg))
-        })?;
+        (StatusCode::NOT_FOUND, Json(msg))
+    })?;
 
     let data = Data {
         channel_name: result.get("channel_name"),
         guild_id: result.get("guild_id"),
         guild_name: result.get("guild_name"),
         suppress: result.get("suppress"),
+        added_by: result.get("added_by"),
+        last_seen_at: result.get("last_seen_at"),
         ..Default::default()
     };
 
-    Ok(Json(data))
+    let added_at: DateTime<Utc> = result.get("added_at");
+    let tag = etag::etag(&(
+        channel_id,
+        &data.channel_name,
+        data.guild_id,
+        &data.guild_name,
+        added_at,
+        data.added_by,
+        data.suppress,
+        data.last_seen_at,
+    ));
+
+    if etag::if_none_match(&request_headers, &tag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, tag)]).into_response());
+    }
+
+    let updated_at: DateTime<Utc> = result.get("updated_at");
+    let last_modified =
+        HeaderValue::from_str(&http_date(updated_at)).expect("HTTP date is a valid header value");
+
+    Ok((
+        [(header::ETAG, tag), (header::LAST_MODIFIED, last_modified)],
+        Json(data),
+    )
+        .into_response())
 }
 
-pub async fn update(
-    State(pool): State<Arc<Pool>>,
-    Path(channel_id): Path<i64>,
-    Json(payload): Json<Data>,
-) -> Result<StatusCode, (StatusCode, Json<Message>)> {
+#[utoipa::path(
+    get,
+    path = "/channel/{channelid}/full",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    responses(
+        (status = 200, description = "Channel and its AniList subscriptions, which may be empty", body = ChannelDetail),
+        (status = 404, description = "Channel not found", body = Message),
+    )
+)]
+pub async fn get_full(
+    State(Replica(pool)): State<Replica>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+) -> Result<Json<ChannelDetail>, (StatusCode, Json<Message>)> {
     let pool = Arc::clone(&pool);
-    let con = pool
-        .get()
-        .await
-        .map_err(|err| internal_error(Box::new(err)))?;
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
 
-    // TODO: add more fields
-    let statement = con
-        .prepare("UPDATE channels SET suppress = CASE WHEN $1::BOOLEAN IS NOT NULL THEN $1 ELSE suppress END WHERE channel_id = $2")
-        .await
-        .map_err(|err| {
-            let db_error = DbError::clone(err.as_db_error().unwrap());
-            internal_error(Box::new(db_error))
-        })?;
+    let channel = query_opt(
+        &con,
+        concat!(
+            "SELECT ",
+            channel_columns!(),
+            " FROM channels WHERE channel_id = $1 AND deleted_at IS NULL"
+        ),
+        &[&channel_id],
+    )
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(|| {
+        let msg = Message {
+            message: i18n::not_found(channel_id),
+            code: Some("not_found".to_string()),
+            ..Default::default()
+        };
+
+        (StatusCode::NOT_FOUND, Json(msg))
+    })?;
 
-    let _result = con
-        .execute(&statement, &[&payload.suppress, &channel_id])
+    let users = query(&con, "SELECT anilist_id, anilist_name, site_url, channel_id, added_by FROM anilist WHERE channel_id = $1 ORDER BY anilist_id", &[&channel_id])
         .await
-        .map_err(|err| {
-            let db_error = DbError::clone(err.as_db_error().unwrap());
-            internal_error(Box::new(db_error))
-        })?;
+        .map_err(internal_error)?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(ChannelDetail {
+        channel: Data::from(&channel),
+        anilist: users.iter().map(UserData::from).collect(),
+    }))
 }
 
-pub async fn delete(
-    State(pool): State<Arc<Pool>>,
-    Path(channel_id): Path<i64>,
-) -> Result<StatusCode, (StatusCode, Json<Message>)> {
+#[utoipa::path(
+    get,
+    path = "/channel/{channelid}/detail",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID"), audit::AuditOptions),
+    responses(
+        (status = 200, description = "Channel and its most recent audit entries, newest first", body = ChannelHistory),
+        (status = 401, description = "Missing or invalid API key", body = Message),
+        (status = 404, description = "No such channel and no history of one", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_detail(
+    State(Replica(pool)): State<Replica>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+    Query(options): Query<audit::AuditOptions>,
+) -> Result<Json<ChannelHistory>, (StatusCode, Json<Message>)> {
     let pool = Arc::clone(&pool);
-    let con = pool
-        .get()
-        .await
-        .map_err(|err| internal_error(Box::new(err)))?;
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
 
-    let statement = con
-        .prepare("DELETE FROM channels WHERE channel_id = $1")
+    let channel = query_opt(
+        &con,
+        concat!(
+            "SELECT ",
+            channel_columns!(),
+            " FROM channels WHERE channel_id = $1 AND deleted_at IS NULL"
+        ),
+        &[&channel_id],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let history = audit::recent(&con, Some(channel_id.0), &options)
         .await
-        .map_err(|err| {
-            let db_error = DbError::clone(err.as_db_error().unwrap());
-            internal_error(Box::new(db_error))
-        })?;
+        .map_err(internal_error)?;
+
+    if channel.is_none() && history.is_empty() {
+        let msg = Message {
+            message: i18n::not_found(channel_id),
+            code: Some("not_found".to_string()),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::NOT_FOUND, Json(msg)));
+    }
+
+    Ok(Json(ChannelHistory {
+        channel: channel.as_ref().map(Data::from),
+        history,
+    }))
+}
 
-    let _result = con
-        .execute(&statement, &[&channel_id])
+// Maps the requested names onto GET_FIELDS, so only our own string
+// literals reach the SQL. Keeping GET_FIELDS order also keeps the number of
+// distinct statements in the cache small.
+#[allow(clippy::result_large_err)]
+fn parse_fields(fields: &str) -> Result<Vec<&'static str>, (StatusCode, Json<Message>)> {
+    let requested: Vec<&str> = fields.split(',').map(str::trim).collect();
+    let unknown: Vec<String> = requested
+        .iter()
+        .filter(|field| !GET_FIELDS.contains(field))
+        .map(|field| format!("{field}: unknown field"))
+        .collect();
+
+    if !unknown.is_empty() {
+        let msg = Message {
+            message: format!("fields must be a subset of {}", GET_FIELDS.join(", ")),
+            code: Some("validation_error".to_string()),
+            data: Some(unknown),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::BAD_REQUEST, Json(msg)));
+    }
+
+    Ok(GET_FIELDS
+        .into_iter()
+        .filter(|field| requested.contains(field))
+        .collect())
+}
+
+async fn get_fields(
+    pool: &Pool,
+    channel_id: ChannelId,
+    fields: &[&str],
+    request_headers: &HeaderMap,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let sql = format!(
+        "SELECT {}, updated_at FROM channels WHERE channel_id = $1 AND deleted_at IS NULL",
+        fields.join(", ")
+    );
+    let result = query_opt(&con, &sql, &[&channel_id])
         .await
-        .map_err(|err| {
-            let db_error = DbError::clone(err.as_db_error().unwrap());
-            internal_error(Box::new(db_error))
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let msg = Message {
+                message: i18n::not_found(channel_id),
+                code: Some("not_found".to_string()),
+                ..Default::default()
+            };
+
+            (StatusCode::NOT_FOUND, Json(msg))
         })?;
 
-    Ok(StatusCode::OK)
-}
+    let selected = |field| fields.contains(&field);
+    let data = Data {
+        channel_name: selected("channel_name").then(|| result.get("channel_name")),
+        guild_id: selected("guild_id").then(|| result.get("guild_id")),
+        guild_name: selected("guild_name").then(|| result.get("guild_name")),
+        suppress: selected("suppress").then(|| result.get("suppress")),
+        added_by: selected("added_by").then(|| result.get("added_by")),
+        last_seen_at: selected("last_seen_at")
+            .then(|| result.get("last_seen_at"))
+            .flatten(),
+        ..Default::default()
+    };
 
-// ------------------------------------------------
-// Testing
-// ------------------------------------------------
+    let tag = etag::etag(&(
+        channel_id,
+        fields,
+        &data.channel_name,
+        data.guild_id,
+        &data.guild_name,
+        data.suppress,
+        data.added_by,
+        data.last_seen_at,
+    ));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{channel, tests::pool};
-    use axum::{
-        body::Body,
-        http::{Request, StatusCode},
-        routing::{delete, get, post, put},
-        Router,
+    if etag::if_none_match(request_headers, &tag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, tag)]).into_response());
+    }
+
+    let updated_at: DateTime<Utc> = result.get("updated_at");
+    let last_modified =
+        HeaderValue::from_str(&http_date(updated_at)).expect("HTTP date is a valid header value");
+
+    Ok((
+        [(header::ETAG, tag), (header::LAST_MODIFIED, last_modified)],
+        Json(data),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel",
+    tag = "channels",
+    params(ListOptions),
+    responses(
+        (status = 200, description = "Channels ordered by when they were added", headers(("X-Total-Count" = i64, description = "Channels matching from and to across all pages"), ("X-Next-Cursor" = String, description = "Cursor of the next page, only sent with text/csv"), ("Last-Modified" = String, description = "Latest change to any channel")), content(
+            (ChannelPage = "application/json"),
+            (String = "text/csv"),
+        )),
+        (status = 304, description = "No channel changed since If-Modified-Since"),
+        (status = 400, description = "Malformed from or to date, cursor, or a negative offset", body = Message),
+    )
+)]
+pub async fn list(
+    State(Replica(pool)): State<Replica>,
+    Query(options): Query<ListOptions>,
+    request_headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    let from = match options.from.as_deref() {
+        Some(value) => Some(parse_date("from", value)?),
+        None => None,
     };
-    use http_body_util::BodyExt;
-    use rand::{distributions::Alphanumeric, random, thread_rng, Rng};
-    use serde_json::{json, to_string, Value};
-    use tower::{Service, ServiceExt};
+    let to = match options.to.as_deref() {
+        Some(value) => Some(parse_date("to", value)?),
+        None => from.map(|_| Utc::now()),
+    };
+    let cursor = match options.cursor.as_deref() {
+        Some(value) => Some(decode_cursor(value)?),
+        None => None,
+    };
+    validate_offset(options.offset)?;
+    let limit = options.limit.clamp(1, LIST_MAX_LIMIT);
+    // One extra row tells us whether there's a next page.
+    let fetch = limit + 1;
 
-    async fn init() -> Router {
-        let pool = pool();
-        let con = pool.get().await.unwrap();
-        con.simple_query(
-            "CREATE TABLE IF NOT EXISTS channels (
-            channel_id BIGINT NOT NULL PRIMARY KEY,
-            channel_name TEXT NOT NULL,
-            guild_id BIGINT NOT NULL,
-            guild_name TEXT NOT NULL,
-            added_at TIMESTAMPTZ NOT NULL,
-            added_by BIGINT NOT NULL,
-            suppress BOOLEAN NOT NULL
-            )",
-        )
-        .await
-        .unwrap();
-        con.simple_query("DELETE FROM channels").await.unwrap();
+    let mut conditions = vec!["deleted_at IS NULL".to_string()];
+    let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+    match (&from, &to) {
+        (Some(from), Some(to)) => {
+            params.push(from);
+            params.push(to);
+            conditions.push(format!(
+                "added_at BETWEEN ${} AND ${}",
+                params.len() - 1,
+                params.len()
+            ));
+        }
+        (None, Some(to)) => {
+            params.push(to);
+            conditions.push(format!("added_at <= ${}", params.len()));
+        }
+        _ => {}
+    }
+    if let Some(suppress) = &options.suppress {
+        params.push(suppress);
+        conditions.push(format!("suppress = ${}", params.len()));
+    }
+    // The total ignores the cursor, limit and offset so it stays the same on
+    // every page.
+    let count_sql = format!(
+        "SELECT COUNT(*) FROM channels WHERE {}",
+        conditions.join(" AND ")
+    );
+    let count_params = params.len();
+    if let Some((added_at, channel_id)) = &cursor {
+        params.push(added_at);
+        params.push(channel_id);
+        conditions.push(format!(
+            "(added_at, channel_id) > (${}, ${})",
+            params.len() - 1,
+            params.len()
+        ));
+    }
+    params.push(&fetch);
+    params.push(&options.offset);
 
-        let arc_pool = Arc::new(pool);
-        Router::new()
+    let sql = format!(
+        "SELECT channel_id, channel_name, guild_id, guild_name, added_at, suppress FROM channels WHERE {} ORDER BY added_at, channel_id LIMIT ${} OFFSET ${}",
+        conditions.join(" AND "),
+        params.len() - 1,
+        params.len()
+    );
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    // Taken over the whole table rather than the page, so it also moves when
+    // a channel leaves the page. Hard deletes are stamped in
+    // channels_changed_at by a trigger.
+    let modified: Option<DateTime<Utc>> = query_one(
+        &con,
+        "SELECT GREATEST(MAX(updated_at), MAX(deleted_at), (SELECT changed_at FROM channels_changed_at)) FROM channels",
+        &[],
+    )
+    .await
+    .map_err(internal_error)?
+    .get(0);
+    let last_modified = modified.map(|modified| {
+        HeaderValue::from_str(&http_date(modified)).expect("HTTP date is a valid header value")
+    });
+
+    // HTTP dates have no fractional seconds, so anything within the second
+    // of If-Modified-Since counts as unmodified.
+    if let (Some(modified), Some(last_modified), Some(since)) = (
+        modified,
+        &last_modified,
+        if_modified_since(&request_headers),
+    ) {
+        if modified.trunc_subsecs(0) <= since {
+            let headers = [(header::LAST_MODIFIED, last_modified.clone())];
+            return Ok((StatusCode::NOT_MODIFIED, headers).into_response());
+        }
+    }
+
+    let result = query(&con, &sql, &params).await.map_err(internal_error)?;
+
+    let total: i64 = query_one(&con, &count_sql, &params[..count_params])
+        .await
+        .map_err(internal_error)?
+        .get(0);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(X_TOTAL_COUNT, HeaderValue::from(total));
+    if let Some(last_modified) = last_modified {
+        headers.insert(header::LAST_MODIFIED, last_modified);
+    }
+
+    let limit = usize::try_from(limit).expect("limit is clamped to a positive value");
+    let next_cursor = match result.get(limit) {
+        Some(_) => {
+            let last = &result[limit - 1];
+            Some(encode_cursor(last.get("added_at"), last.get("channel_id")))
+        }
+        _ => None,
+    };
+
+    let items: Vec<Data> = result
+        .iter()
+        .take(limit)
+        .map(|row| Data {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+            guild_id: row.get("guild_id"),
+            guild_name: row.get("guild_name"),
+            suppress: row.get("suppress"),
+            ..Default::default()
+        })
+        .collect();
+
+    if accept::names(&request_headers, CSV) {
+        // CSV has nowhere to put the cursor, so it goes in a header instead.
+        if let Some(cursor) = &next_cursor {
+            headers.insert(
+                X_NEXT_CURSOR,
+                HeaderValue::from_str(cursor).expect("base64 is a valid header value"),
+            );
+        }
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(CSV));
+        return Ok((headers, to_csv(&result[..items.len()])?).into_response());
+    }
+
+    Ok((headers, Json(ChannelPage { items, next_cursor })).into_response())
+}
+
+const CSV: &str = "text/csv";
+
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    channel_id: ChannelId,
+    channel_name: &'a str,
+    guild_id: GuildId,
+    guild_name: &'a str,
+    added_at: String,
+    suppress: bool,
+}
+
+#[allow(clippy::result_large_err)]
+fn to_csv(rows: &[Row]) -> Result<String, (StatusCode, Json<Message>)> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        let added_at: DateTime<Utc> = row.get("added_at");
+        let record = CsvRow {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+            guild_id: row.get("guild_id"),
+            guild_name: row.get("guild_name"),
+            added_at: added_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            suppress: row.get("suppress"),
+        };
+        writer.serialize(record).map_err(|err| server_error(&err))?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|err| server_error(&err.into_error()))?;
+    Ok(String::from_utf8(bytes).expect("CSV of UTF-8 fields is UTF-8"))
+}
+
+const NDJSON: &str = "application/x-ndjson";
+
+// Streams rows to the client as Postgres returns them, so backing up the
+// whole table doesn't hold it all in memory at once.
+#[utoipa::path(
+    get,
+    path = "/channel/export",
+    tag = "channels",
+    responses(
+        (status = 200, description = "Every channel ordered by channel_id, one Data object per line", body = Data, content_type = "application/x-ndjson"),
+    )
+)]
+pub async fn export_stream(
+    State(Replica(pool)): State<Replica>,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let rows = query_raw(
+        &con,
+        concat!(
+            "SELECT ",
+            channel_columns!(),
+            " FROM channels WHERE deleted_at IS NULL ORDER BY channel_id"
+        ),
+        &[],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    // The closure owns the connection, so it only goes back to the pool once
+    // the last row is sent or the client hangs up.
+    let as_strings = types::snowflakes_as_strings();
+    let lines = rows.map(move |row| {
+        let _con = &con;
+        let data = Data::from(&row?);
+        let mut line = types::with_snowflakes_as_strings(as_strings, || serde_json::to_vec(&data))?;
+        line.push(b'\n');
+        Ok::<_, BoxError>(line)
+    });
+
+    Ok(([(header::CONTENT_TYPE, NDJSON)], Body::from_stream(lines)).into_response())
+}
+
+// Cursors are the (added_at, channel_id) keyset of the last row on a page.
+fn encode_cursor(added_at: DateTime<Utc>, channel_id: i64) -> String {
+    let key = format!(
+        "{}|{channel_id}",
+        added_at.to_rfc3339_opts(SecondsFormat::Micros, true)
+    );
+    URL_SAFE_NO_PAD.encode(key)
+}
+
+#[allow(clippy::result_large_err)]
+fn decode_cursor(cursor: &str) -> Result<(DateTime<Utc>, i64), (StatusCode, Json<Message>)> {
+    let key = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    let parsed = key.as_deref().and_then(|key| {
+        let (added_at, channel_id) = key.split_once('|')?;
+        let added_at = DateTime::parse_from_rfc3339(added_at).ok()?;
+        Some((added_at.with_timezone(&Utc), channel_id.parse().ok()?))
+    });
+
+    parsed.ok_or_else(|| {
+        let msg = Message {
+            message: "cursor is invalid".to_string(),
+            code: Some("validation_error".to_string()),
+            ..Default::default()
+        };
+
+        (StatusCode::BAD_REQUEST, Json(msg))
+    })
+}
+
+#[allow(clippy::result_large_err)]
+fn parse_date(field: &str, value: &str) -> Result<DateTime<Utc>, (StatusCode, Json<Message>)> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|err| {
+            let msg = Message {
+                message: format!("{field} is not an RFC 3339 date: {err}"),
+                code: Some("validation_error".to_string()),
+                ..Default::default()
+            };
+
+            (StatusCode::BAD_REQUEST, Json(msg))
+        })
+}
+
+fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+#[allow(clippy::result_large_err)]
+fn if_unmodified_since(
+    headers: &HeaderMap,
+) -> Result<Option<DateTime<Utc>>, (StatusCode, Json<Message>)> {
+    let Some(value) = headers.get(header::IF_UNMODIFIED_SINCE) else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .map(|date| Some(date.with_timezone(&Utc)))
+        .ok_or_else(|| {
+            let msg = Message {
+                message: "If-Unmodified-Since is not an HTTP date".to_string(),
+                code: Some("validation_error".to_string()),
+                ..Default::default()
+            };
+
+            (StatusCode::BAD_REQUEST, Json(msg))
+        })
+}
+
+// Unlike If-Unmodified-Since, an unparsable If-Modified-Since is ignored
+// (RFC 9110 13.1.3) and the full response sent.
+fn if_modified_since(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    headers
+        .get(header::IF_MODIFIED_SINCE)?
+        .to_str()
+        .ok()
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .map(|date| date.with_timezone(&Utc))
+}
+
+fn modified_since_message(channel_id: ChannelId) -> (StatusCode, Json<Message>) {
+    let msg = Message {
+        message: format!("{channel_id} was modified after If-Unmodified-Since"),
+        code: Some("conflict".to_string()),
+        ..Default::default()
+    };
+
+    (StatusCode::CONFLICT, Json(msg))
+}
+
+// Postgres rejects a negative OFFSET, so it's caught here as the client's
+// mistake rather than surfacing as a database error.
+#[allow(clippy::result_large_err)]
+pub(crate) fn validate_offset(offset: i64) -> Result<(), (StatusCode, Json<Message>)> {
+    if offset >= 0 {
+        return Ok(());
+    }
+
+    let msg = Message {
+        message: format!("offset must not be negative, got {offset}"),
+        code: Some("validation_error".to_string()),
+        ..Default::default()
+    };
+
+    Err((StatusCode::BAD_REQUEST, Json(msg)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/search",
+    tag = "channels",
+    params(SearchOptions),
+    responses(
+        (status = 200, description = "Channels whose name contains q", body = Vec<Data>),
+        (status = 400, description = "Empty search query", body = Message),
+    )
+)]
+pub async fn search(
+    State(Replica(pool)): State<Replica>,
+    Query(options): Query<SearchOptions>,
+) -> Result<Json<Vec<Data>>, (StatusCode, Json<Message>)> {
+    if options.q.is_empty() {
+        let msg = Message {
+            message: "Search query q must not be empty".to_string(),
+            code: Some("validation_error".to_string()),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::BAD_REQUEST, Json(msg)));
+    }
+
+    let limit = options.limit.clamp(1, SEARCH_MAX_LIMIT);
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(
+        &con,
+        "SELECT channel_id, channel_name, guild_id, guild_name, suppress FROM channels WHERE channel_name ILIKE '%' || $1 || '%' AND deleted_at IS NULL ORDER BY channel_name LIMIT $2",
+        &[&escape_like(&options.q), &limit],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let channels = result
+        .iter()
+        .map(|row| Data {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+            guild_id: row.get("guild_id"),
+            guild_name: row.get("guild_name"),
+            suppress: row.get("suppress"),
+            ..Default::default()
+        })
+        .collect();
+
+    Ok(Json(channels))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/autocomplete",
+    tag = "channels",
+    params(AutocompleteOptions),
+    responses(
+        (status = 200, description = "Up to 10 channels whose name starts with q", body = Vec<ChannelName>),
+        (status = 400, description = "Empty search query", body = Message),
+    )
+)]
+pub async fn autocomplete(
+    State(Replica(pool)): State<Replica>,
+    Query(options): Query<AutocompleteOptions>,
+) -> Result<Json<Vec<ChannelName>>, (StatusCode, Json<Message>)> {
+    if options.q.is_empty() {
+        let msg = Message {
+            message: "Search query q must not be empty".to_string(),
+            code: Some("validation_error".to_string()),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::BAD_REQUEST, Json(msg)));
+    }
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    // Matches the lower(channel_name) text_pattern_ops index, which ILIKE can't use.
+    let result = query(&con, "SELECT channel_id, channel_name FROM channels WHERE lower(channel_name) LIKE lower($1) || '%' AND deleted_at IS NULL ORDER BY lower(channel_name) LIMIT 10", &[&escape_like(&options.q)])
+        .await
+        .map_err(internal_error)?;
+
+    let channels = result
+        .iter()
+        .map(|row| ChannelName {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+        })
+        .collect();
+
+    Ok(Json(channels))
+}
+
+// Unlike list this has no filters or paging, and only ever returns these
+// four fields, so bots can rely on its shape.
+#[utoipa::path(
+    get,
+    path = "/channel/recent",
+    tag = "channels",
+    params(RecentOptions),
+    responses(
+        (status = 200, description = "Most recently added channels, newest first", body = Vec<RecentChannel>),
+    )
+)]
+pub async fn recent(
+    State(Replica(pool)): State<Replica>,
+    Query(options): Query<RecentOptions>,
+) -> Result<Json<Vec<RecentChannel>>, (StatusCode, Json<Message>)> {
+    let limit = options.limit.clamp(1, RECENT_MAX_LIMIT);
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(&con, "SELECT channel_id, channel_name, guild_name, added_at FROM channels WHERE deleted_at IS NULL ORDER BY added_at DESC, channel_id DESC LIMIT $1", &[&limit])
+        .await
+        .map_err(internal_error)?;
+
+    let channels = result
+        .iter()
+        .map(|row| RecentChannel {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+            guild_name: row.get("guild_name"),
+            added_at: row.get("added_at"),
+        })
+        .collect();
+
+    Ok(Json(channels))
+}
+
+// Ties go to the lower channel_id so the order is stable between calls.
+#[utoipa::path(
+    get,
+    path = "/channel/top",
+    tag = "channels",
+    params(TopOptions),
+    responses(
+        (status = 200, description = "Channels with the most AniList subscriptions first", body = Vec<TopChannel>),
+    )
+)]
+pub async fn most_subscribed(
+    State(Replica(pool)): State<Replica>,
+    Query(options): Query<TopOptions>,
+) -> Result<Json<Vec<TopChannel>>, (StatusCode, Json<Message>)> {
+    let limit = options.limit.clamp(1, TOP_MAX_LIMIT);
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(
+        &con,
+        "SELECT channel_id, channel_name, COUNT(anilist.channel_id) AS subscription_count \
+         FROM channels LEFT JOIN anilist USING (channel_id) \
+         WHERE deleted_at IS NULL \
+         GROUP BY channel_id, channel_name \
+         HAVING COUNT(anilist.channel_id) >= $1 \
+         ORDER BY subscription_count DESC, channel_id \
+         LIMIT $2",
+        &[&options.min, &limit],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let channels = result
+        .iter()
+        .map(|row| TopChannel {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+            subscription_count: row.get("subscription_count"),
+        })
+        .collect();
+
+    Ok(Json(channels))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/events",
+    tag = "channels",
+    responses(
+        (status = 200, description = "Server-sent events named create, update or delete, each carrying {operation, channel_id}", content_type = "text/event-stream"),
+    )
+)]
+pub async fn events() -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    // A receiver that lagged behind just skips the events it missed.
+    let as_strings = types::snowflakes_as_strings();
+    let stream = BroadcastStream::new(events::subscribe())
+        .filter_map(Result::ok)
+        .map(move |event| {
+            types::with_snowflakes_as_strings(as_strings, || {
+                Event::default()
+                    .event(event.operation.as_str())
+                    .json_data(event)
+            })
+        });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+// Makes % and _ match literally. Backslash is the default LIKE escape
+// character in Postgres, so it is escaped first.
+pub(crate) fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// Same lookup as get without fetching the row, for clients that only need
+// to know whether a channel is registered. Routed through get, since axum
+// would otherwise list HEAD twice in the Allow header.
+#[utoipa::path(
+    head,
+    path = "/channel/{channelid}",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    responses(
+        (status = 200, description = "Channel exists"),
+        (status = 404, description = "Channel not found"),
+    )
+)]
+pub async fn exists(
+    State(Replica(pool)): State<Replica>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+) -> Result<StatusCode, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let found = query_opt(
+        &con,
+        "SELECT 1 FROM channels WHERE channel_id = $1 AND deleted_at IS NULL",
+        &[&channel_id],
+    )
+    .await
+    .map_err(internal_error)?
+    .is_some();
+
+    if found {
+        Ok(StatusCode::OK)
+    } else {
+        Ok(StatusCode::NOT_FOUND)
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/batch-get",
+    tag = "channels",
+    request_body = Vec<ChannelId>,
+    responses(
+        (status = 200, description = "Channels that exist, IDs not found are omitted", body = Vec<Data>),
+        (status = 400, description = "Malformed body", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_many(
+    State(Replica(pool)): State<Replica>,
+    ValidatedJson(channel_ids): ValidatedJson<Vec<ChannelId>>,
+) -> Result<Json<Vec<Data>>, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .max_items("channel_ids", channel_ids.len())
+        .finish()?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(
+        &con,
+        concat!(
+            "SELECT ",
+            channel_columns!(),
+            " FROM channels WHERE channel_id = ANY($1) AND deleted_at IS NULL ORDER BY channel_id"
+        ),
+        &[&channel_ids],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let channels = result.iter().map(Data::from).collect();
+
+    Ok(Json(channels))
+}
+
+#[utoipa::path(
+    put,
+    path = "/channel/{channelid}",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    request_body = Replace,
+    responses(
+        (status = 201, description = "Channel created", headers(("Location" = String, description = "URL of the new channel"))),
+        (status = 200, description = "Channel replaced"),
+        (status = 400, description = "Malformed body or the path ID is not a valid snowflake", body = Message),
+        (status = 409, description = "Channel was modified after If-Unmodified-Since", body = Message),
+        (status = 422, description = "Invalid fields, one per entry in data", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn put_replace(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+    request_headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<Replace>,
+) -> Result<(StatusCode, HeaderMap), (StatusCode, Json<Message>)> {
+    validate_snowflake("channel_id", channel_id)?;
+    let unmodified_since = if_unmodified_since(&request_headers)?;
+    FieldErrors::default()
+        .not_empty("channel_name", &payload.channel_name)
+        .snowflake("guild_id", payload.guild_id)
+        .snowflake("added_by", payload.added_by)
+        .finish()?;
+    validate_snowflake_epoch("channel_id", channel_id)?;
+    validate_snowflake_epoch("guild_id", payload.guild_id)?;
+    validate_snowflake_epoch("added_by", payload.added_by)?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    // xmax is only zero for a freshly inserted row. added_at keeps its
+    // original value on replace. HTTP dates have second precision, so
+    // updated_at is truncated before comparing against them. A failed
+    // check skips the update and returns no row.
+    let result = query_opt(&con, concat!(
+            insert_columns!("NOW()"),
+            "$6) ON CONFLICT (channel_id) DO UPDATE SET channel_name = EXCLUDED.channel_name, guild_id = EXCLUDED.guild_id, guild_name = EXCLUDED.guild_name, added_by = EXCLUDED.added_by, suppress = EXCLUDED.suppress, deleted_at = NULL, updated_at = NOW() WHERE $7::TIMESTAMPTZ IS NULL OR date_trunc('second', channels.updated_at) <= $7 RETURNING (xmax = 0) AS inserted"
+        ), &[
+                &channel_id,
+                &payload.channel_name,
+                &payload.guild_id,
+                &guild_name_or_unknown(payload.guild_name),
+                &payload.added_by,
+                &payload.suppress.unwrap_or_default(),
+                &unmodified_since,
+            ])
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| modified_since_message(channel_id))?;
+
+    if !result.get::<_, bool>("inserted") {
+        events::publish(Operation::Update, channel_id);
+        return Ok((StatusCode::OK, HeaderMap::new()));
+    }
+    events::publish(Operation::Create, channel_id);
+
+    let mut headers = HeaderMap::new();
+    let location = format!("/channel/{channel_id}");
+    headers.insert(
+        header::LOCATION,
+        HeaderValue::from_str(&location).expect("channel URL is a valid header value"),
+    );
+
+    Ok((StatusCode::CREATED, headers))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/channel/{channelid}",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    request_body(content = ChannelPatch, description = "application/json, or application/merge-patch+json for RFC 7396 semantics"),
+    responses(
+        (status = 200, description = "Channel updated", body = Mutation),
+        (status = 400, description = "Malformed body, or a field PATCH can't change", body = Message),
+        (status = 404, description = "Channel not found", body = Message),
+        (status = 409, description = "Update rejected by the database, or the channel was modified after If-Unmodified-Since", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+    request: Request,
+) -> Result<Json<Mutation>, (StatusCode, Json<Message>)> {
+    let unmodified_since = if_unmodified_since(request.headers())?;
+    // RFC 7396: absent members are untouched and null resets a member,
+    // suppress to false and guild_name to "Unknown", as neither column can be
+    // empty.
+    let merge = is_merge_patch(request.headers());
+    let ValidatedJson(payload) = ValidatedJson::<ChannelPatch>::from_request(request, &()).await?;
+    let suppress = match payload.suppress {
+        Some(None) if merge => Some(false),
+        suppress => suppress.flatten(),
+    };
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    // A cleared guild_name becomes UNKNOWN_GUILD_NAME, the same as on create.
+    let guild_name = payload
+        .guild_name
+        .map(|name| guild_name_or_unknown(Some(name)));
+
+    // TODO: add more fields
+    let updated = query_opt(&con, "UPDATE channels SET suppress = CASE WHEN $1::BOOLEAN IS NOT NULL THEN $1 ELSE suppress END, guild_name = COALESCE($4, guild_name), updated_at = NOW() WHERE channel_id = $2 AND deleted_at IS NULL AND ($3::TIMESTAMPTZ IS NULL OR date_trunc('second', updated_at) <= $3) RETURNING updated_at", &[
+                &suppress,
+                &channel_id,
+                &unmodified_since,
+                &guild_name,
+            ])
+        .await
+        .map_err(internal_error)?;
+
+    // Nothing matched either because the channel is missing or because the
+    // precondition failed, only the latter is a conflict.
+    if updated.is_none() && unmodified_since.is_some() {
+        let exists = query_opt(
+            &con,
+            "SELECT 1 FROM channels WHERE channel_id = $1 AND deleted_at IS NULL",
+            &[&channel_id],
+        )
+        .await
+        .map_err(internal_error)?
+        .is_some();
+
+        if exists {
+            return Err(modified_since_message(channel_id));
+        }
+    }
+    let row = updated.ok_or_else(|| {
+        let msg = Message {
+            message: i18n::not_found(channel_id),
+            code: Some("not_found".to_string()),
+            ..Default::default()
+        };
+
+        (StatusCode::NOT_FOUND, Json(msg))
+    })?;
+    events::publish(Operation::Update, channel_id);
+
+    let mutation = Mutation {
+        suppress: None,
+        updated_at: row.get("updated_at"),
+    };
+
+    Ok(Json(mutation))
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/{channelid}/suppress/toggle",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    responses(
+        (status = 200, description = "New suppress value", body = Mutation),
+        (status = 404, description = "Channel not found", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn toggle_suppress(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+) -> Result<Json<Mutation>, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query_opt(&con, "UPDATE channels SET suppress = NOT suppress, updated_at = NOW() WHERE channel_id = $1 AND deleted_at IS NULL RETURNING suppress, updated_at", &[&channel_id])
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let msg = Message {
+                message: i18n::not_found(channel_id),
+                code: Some("not_found".to_string()),
+                ..Default::default()
+            };
+
+            (StatusCode::NOT_FOUND, Json(msg))
+        })?;
+
+    events::publish(Operation::Update, channel_id);
+
+    let mutation = Mutation {
+        suppress: result.get("suppress"),
+        updated_at: result.get("updated_at"),
+    };
+
+    Ok(Json(mutation))
+}
+
+// Reassigns the channel to another user, e.g. when whoever registered it
+// leaves the guild.
+#[utoipa::path(
+    put,
+    path = "/channel/{channelid}/owner",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    request_body = Owner,
+    responses(
+        (status = 200, description = "Owner changed", body = Mutation),
+        (status = 400, description = "Malformed body", body = Message),
+        (status = 404, description = "Channel not found", body = Message),
+        (status = 422, description = "added_by is not a positive snowflake", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn transfer_owner(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+    ValidatedJson(payload): ValidatedJson<Owner>,
+) -> Result<Json<Mutation>, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .snowflake("added_by", payload.added_by)
+        .finish()?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query_opt(&con, "UPDATE channels SET added_by = $1, updated_at = NOW() WHERE channel_id = $2 AND deleted_at IS NULL RETURNING updated_at", &[&payload.added_by, &channel_id])
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let msg = Message {
+                message: i18n::not_found(channel_id),
+                code: Some("not_found".to_string()),
+                ..Default::default()
+            };
+
+            (StatusCode::NOT_FOUND, Json(msg))
+        })?;
+
+    events::publish(Operation::Update, channel_id);
+
+    let mutation = Mutation {
+        suppress: None,
+        updated_at: result.get("updated_at"),
+    };
+
+    Ok(Json(mutation))
+}
+
+#[utoipa::path(
+    put,
+    path = "/guild/{guildid}/suppress",
+    tag = "channels",
+    params(("guildid" = i64, Path, description = "Discord guild ID")),
+    request_body = GuildSuppress,
+    responses(
+        (status = 200, description = "IDs of the updated channels, in data", body = Message),
+        (status = 400, description = "Malformed body", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn set_guild_suppress(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(guild_id): ValidatedPath<GuildId>,
+    ValidatedJson(payload): ValidatedJson<GuildSuppress>,
+) -> Result<Json<Message>, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(&con, "UPDATE channels SET suppress = $1, updated_at = NOW() WHERE guild_id = $2 AND deleted_at IS NULL RETURNING channel_id", &[&payload.suppress, &guild_id])
+        .await
+        .map_err(internal_error)?;
+
+    let channel_ids: Vec<String> = result
+        .iter()
+        .map(|row| {
+            let channel_id: ChannelId = row.get("channel_id");
+            events::publish(Operation::Update, channel_id);
+            channel_id.to_string()
+        })
+        .collect();
+
+    let msg = Message {
+        message: format!("Updated {} channels", channel_ids.len()),
+        data: Some(channel_ids),
+        ..Default::default()
+    };
+
+    Ok(Json(msg))
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/suppress",
+    tag = "channels",
+    request_body = SuppressMany,
+    responses(
+        (status = 200, description = "IDs of the updated channels, in data", body = Message),
+        (status = 400, description = "Malformed body", body = Message),
+        (status = 422, description = "Too many channel IDs", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn set_suppress_many(
+    State(pool): State<Arc<Pool>>,
+    ValidatedJson(payload): ValidatedJson<SuppressMany>,
+) -> Result<Json<Message>, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .max_items("channel_ids", payload.channel_ids.len())
+        .finish()?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    // IDs that don't match a live channel are skipped, only the ones
+    // actually updated come back.
+    let result = query(&con, "UPDATE channels SET suppress = $2, updated_at = NOW() WHERE channel_id = ANY($1) AND deleted_at IS NULL RETURNING channel_id", &[&payload.channel_ids, &payload.suppress])
+        .await
+        .map_err(internal_error)?;
+
+    let channel_ids: Vec<String> = result
+        .iter()
+        .map(|row| {
+            let channel_id: ChannelId = row.get("channel_id");
+            events::publish(Operation::Update, channel_id);
+            channel_id.to_string()
+        })
+        .collect();
+
+    let msg = Message {
+        message: format!("Updated {} channels", channel_ids.len()),
+        data: Some(channel_ids),
+        ..Default::default()
+    };
+
+    Ok(Json(msg))
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/{channelid}/move/{to}",
+    tag = "channels",
+    params(
+        ("channelid" = i64, Path, description = "Channel to move subscriptions from"),
+        ("to" = i64, Path, description = "Channel to move subscriptions to"),
+    ),
+    responses(
+        (status = 200, description = "Number of subscriptions moved, in data", body = Message),
+        (status = 400, description = "Both channels are the same", body = Message),
+        (status = 404, description = "Either channel not found", body = Message),
+        (status = 409, description = "Target channel already has one of the subscriptions", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn move_subscriptions(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath((from, to)): ValidatedPath<(ChannelId, ChannelId)>,
+) -> Result<Json<Message>, (StatusCode, Json<Message>)> {
+    if from == to {
+        let msg = Message {
+            message: format!("Can't move subscriptions from {from} to itself"),
+            code: Some("validation_error".to_string()),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::BAD_REQUEST, Json(msg)));
+    }
+
+    let moved = with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            // Both rows are locked in channel_id order, so two moves between
+            // the same pair in opposite directions can't deadlock.
+            let locked: Vec<ChannelId> = query(
+                tx,
+                "SELECT channel_id FROM channels WHERE channel_id = ANY($1) AND deleted_at IS NULL ORDER BY channel_id FOR UPDATE",
+                &[&vec![from, to]],
+            )
+            .await
+            .map_err(internal_error)?
+            .iter()
+            .map(|row| row.get("channel_id"))
+            .collect();
+
+            if let Some(missing) = [from, to].into_iter().find(|id| !locked.contains(id)) {
+                let msg = Message {
+                    message: i18n::not_found(missing),
+                    code: Some("not_found".to_string()),
+                    ..Default::default()
+                };
+
+                return Err((StatusCode::NOT_FOUND, Json(msg)));
+            }
+
+            execute(
+                tx,
+                "UPDATE anilist SET channel_id = $2 WHERE channel_id = $1",
+                &[&from, &to],
+            )
+            .await
+            .map_err(internal_error)
+        })
+    })
+    .await?;
+
+    let msg = Message {
+        message: format!("Moved {moved} subscriptions from {from} to {to}"),
+        data: Some(vec![moved.to_string()]),
+        ..Default::default()
+    };
+
+    Ok(Json(msg))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/channel/{channelid}",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID"), DeleteOptions, IdempotentOptions),
+    responses(
+        (status = 200, description = "The deleted channel", body = Data),
+        (status = 204, description = "Channel deleted or already gone, with idempotent=true"),
+        (status = 404, description = "Channel not found", body = Message),
+        (status = 409, description = "Delete rejected by the database", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+    Query(options): Query<DeleteOptions>,
+    Query(idempotent): Query<IdempotentOptions>,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    // anilist rows reference channels, so a hard delete removes them first.
+    let result = if options.hard {
+        with_transaction(&pool, |tx| {
+            Box::pin(async move {
+                execute(
+                    tx,
+                    "DELETE FROM anilist WHERE channel_id = $1",
+                    &[&channel_id],
+                )
+                .await
+                .map_err(internal_error)?;
+
+                query_opt(
+                    tx,
+                    concat!(
+                        "DELETE FROM channels WHERE channel_id = $1 RETURNING ",
+                        channel_columns!()
+                    ),
+                    &[&channel_id],
+                )
+                .await
+                .map_err(internal_error)
+            })
+        })
+        .await?
+    } else {
+        let pool = Arc::clone(&pool);
+        let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+        query_opt(
+            &con,
+            concat!(
+                "UPDATE channels SET deleted_at = NOW() WHERE channel_id = $1 AND deleted_at IS NULL RETURNING ",
+                channel_columns!()
+            ),
+            &[&channel_id],
+        )
+        .await
+        .map_err(internal_error)?
+    };
+
+    if result.is_some() {
+        events::publish(Operation::Delete, channel_id);
+    }
+    if idempotent.idempotent {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    let result = result.ok_or_else(|| {
+        let msg = Message {
+            message: i18n::not_found(channel_id),
+            code: Some("not_found".to_string()),
+            ..Default::default()
+        };
+
+        (StatusCode::NOT_FOUND, Json(msg))
+    })?;
+
+    Ok(Json(Data::from(&result)).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/bulk-delete",
+    tag = "channels",
+    params(DeleteOptions, DryRunOptions),
+    request_body = Vec<ChannelId>,
+    responses(
+        (status = 200, description = "Number of channels deleted, in data", body = Message),
+        (status = 409, description = "Delete rejected by the database", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_bulk(
+    State(pool): State<Arc<Pool>>,
+    Query(options): Query<DeleteOptions>,
+    Query(dry_run): Query<DryRunOptions>,
+    ValidatedJson(channel_ids): ValidatedJson<Vec<ChannelId>>,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .max_items("channel_ids", channel_ids.len())
+        .finish()?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    if dry_run.dry_run {
+        let sql = if options.hard {
+            "SELECT COUNT(*) FROM channels WHERE channel_id = ANY($1)"
+        } else {
+            "SELECT COUNT(*) FROM channels WHERE channel_id = ANY($1) AND deleted_at IS NULL"
+        };
+        let row = query_one(&con, sql, &[&channel_ids])
+            .await
+            .map_err(internal_error)?;
+
+        return Ok(dry_run_message(row.get(0)));
+    }
+
+    // anilist rows reference channels, so a hard delete removes them first,
+    // in a transaction with a connection of its own.
+    let result = if options.hard {
+        drop(con);
+        with_transaction(&pool, |tx| {
+            Box::pin(async move {
+                execute(
+                    tx,
+                    "DELETE FROM anilist WHERE channel_id = ANY($1)",
+                    &[&channel_ids],
+                )
+                .await
+                .map_err(internal_error)?;
+
+                query(
+                    tx,
+                    "DELETE FROM channels WHERE channel_id = ANY($1) RETURNING channel_id",
+                    &[&channel_ids],
+                )
+                .await
+                .map_err(internal_error)
+            })
+        })
+        .await?
+    } else {
+        query(
+            &con,
+            "UPDATE channels SET deleted_at = NOW() WHERE channel_id = ANY($1) AND deleted_at IS NULL RETURNING channel_id",
+            &[&channel_ids],
+        )
+        .await
+        .map_err(internal_error)?
+    };
+
+    for row in &result {
+        events::publish(Operation::Delete, row.get("channel_id"));
+    }
+    let deleted = result.len();
+
+    let msg = Message {
+        message: format!("Deleted {deleted} channels"),
+        data: Some(vec![deleted.to_string()]),
+        ..Default::default()
+    };
+
+    Ok(Json(msg).into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/guild/{guildid}/channel",
+    tag = "channels",
+    params(("guildid" = i64, Path, description = "Discord guild ID"), DryRunOptions, ConfirmOptions),
+    responses(
+        (status = 200, description = "Number of channels deleted, in data", body = Message),
+        (status = 400, description = "confirm is missing or doesn't match the guild ID", body = Message),
+        (status = 409, description = "Delete rejected by the database", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_by_guild(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(guild_id): ValidatedPath<GuildId>,
+    Query(dry_run): Query<DryRunOptions>,
+    Query(confirm): Query<ConfirmOptions>,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    // Wiping a whole guild takes the ID twice, so a mistyped path can't
+    // do it alone. Dry runs delete nothing and go through either way.
+    let confirmed = confirm
+        .confirm
+        .and_then(|value| value.parse::<GuildId>().ok())
+        == Some(guild_id);
+    if !dry_run.dry_run && !confirmed {
+        let msg = Message {
+            message: format!("confirmation required: repeat the guild ID as ?confirm={guild_id}"),
+            code: Some("confirmation_required".to_string()),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::BAD_REQUEST, Json(msg)));
+    }
+
+    if dry_run.dry_run {
+        let pool = Arc::clone(&pool);
+        let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+        let row = query_one(
+            &con,
+            "SELECT COUNT(*) FROM channels WHERE guild_id = $1",
+            &[&guild_id],
+        )
+        .await
+        .map_err(internal_error)?;
+
+        return Ok(dry_run_message(row.get(0)));
+    }
+
+    // anilist rows reference channels, so they have to go first.
+    let rows = with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            execute(
+                tx,
+                "DELETE FROM anilist WHERE channel_id IN (SELECT channel_id FROM channels WHERE guild_id = $1)",
+                &[&guild_id],
+            )
+            .await
+            .map_err(internal_error)?;
+
+            query(
+                tx,
+                "DELETE FROM channels WHERE guild_id = $1 RETURNING channel_id",
+                &[&guild_id],
+            )
+            .await
+            .map_err(internal_error)
+        })
+    })
+    .await?;
+
+    for row in &rows {
+        events::publish(Operation::Delete, row.get("channel_id"));
+    }
+    let deleted = rows.len();
+
+    let msg = Message {
+        message: format!("Deleted {deleted} channels"),
+        data: Some(vec![deleted.to_string()]),
+        ..Default::default()
+    };
+
+    Ok(Json(msg).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/{channelid}/touch",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    responses(
+        (status = 204, description = "last_seen_at set to now"),
+        (status = 404, description = "Channel not found", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn touch(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+) -> Result<StatusCode, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let touched = execute(
+        &con,
+        "UPDATE channels SET last_seen_at = NOW() WHERE channel_id = $1 AND deleted_at IS NULL",
+        &[&channel_id],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    if touched == 0 {
+        let msg = Message {
+            message: i18n::not_found(channel_id),
+            code: Some("not_found".to_string()),
+            ..Default::default()
+        };
+
+        return Err((StatusCode::NOT_FOUND, Json(msg)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/{channelid}/restore",
+    tag = "channels",
+    params(("channelid" = i64, Path, description = "Discord channel ID")),
+    responses(
+        (status = 200, description = "Channel restored", body = Data),
+        (status = 404, description = "Channel is not soft-deleted", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn restore(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(channel_id): ValidatedPath<ChannelId>,
+) -> Result<Json<Data>, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query_opt(&con, "UPDATE channels SET deleted_at = NULL, updated_at = NOW() WHERE channel_id = $1 AND deleted_at IS NOT NULL RETURNING channel_name, guild_id, guild_name, suppress", &[&channel_id])
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let msg = Message {
+                message: i18n::deleted_not_found(channel_id),
+                code: Some("not_found".to_string()),
+                ..Default::default()
+            };
+
+            (StatusCode::NOT_FOUND, Json(msg))
+        })?;
+
+    // The channel reappears in GET responses, so subscribers see it as created.
+    events::publish(Operation::Create, channel_id);
+
+    let data = Data {
+        channel_name: result.get("channel_name"),
+        guild_id: result.get("guild_id"),
+        guild_name: result.get("guild_name"),
+        suppress: result.get("suppress"),
+        ..Default::default()
+    };
+
+    Ok(Json(data))
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audit, channel, tests,
+        util::validation::{scope_admin_fields, DEFAULT_MAX_BULK_ITEMS, DISCORD_EPOCH_MS},
+        AppState,
+    };
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        middleware,
+        routing::{delete, get, patch, post, put, RouterIntoService},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use rand::{distributions::Alphanumeric, random, thread_rng, Rng};
+    use serde_json::{json, to_string, Value};
+    use std::time::Duration;
+    use tower::{Service, ServiceExt};
+
+    async fn init() -> Router {
+        init_with_policy(DuplicatePolicy::default()).await
+    }
+
+    async fn init_with_policy(duplicate_policy: DuplicatePolicy) -> Router {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+
+        let state = AppState {
+            duplicate_policy,
+            ..AppState::from(Arc::new(pool))
+        };
+        Router::new()
             .route("/channel", post(channel::add))
+            .route("/channel", get(channel::list))
+            .route("/channel/search", get(channel::search))
+            .route("/channel/autocomplete", get(channel::autocomplete))
+            .route("/channel/recent", get(channel::recent))
+            .route("/channel/top", get(channel::most_subscribed))
+            .route("/channel/events", get(channel::events))
+            .route("/channel/export", get(channel::export_stream))
             .route("/channel/:channelid", get(channel::get))
-            .route("/channel/:channelid", put(channel::update))
+            .route("/channel/:channelid", put(channel::put_replace))
+            .route("/channel/:channelid", patch(channel::update))
             .route("/channel/:channelid", delete(channel::delete))
-            .with_state(arc_pool)
+            .route("/channel/batch-get", post(channel::get_many))
+            .route("/channel/full", post(channel::register_with_subscriptions))
+            .route("/channel/sync", post(channel::sync))
+            .route("/channel/bulk-delete", post(channel::delete_bulk))
+            .route("/guild/:guildid/channel", delete(channel::delete_by_guild))
+            .route("/guild/:guildid/suppress", put(channel::set_guild_suppress))
+            .route("/channel/suppress", post(channel::set_suppress_many))
+            .route(
+                "/channel/:channelid/suppress/toggle",
+                post(channel::toggle_suppress),
+            )
+            .route("/channel/:channelid/owner", put(channel::transfer_owner))
+            .route("/channel/:channelid/full", get(channel::get_full))
+            .route("/channel/:channelid/detail", get(channel::get_detail))
+            .route("/channel/:channelid/touch", post(channel::touch))
+            .route("/channel/:channelid/restore", post(channel::restore))
+            .route(
+                "/channel/:channelid/move/:to",
+                post(channel::move_subscriptions),
+            )
+            .with_state(state)
+    }
+
+    fn rng_add_channel() -> Create {
+        Create {
+            channel_id: ChannelId(tests::snowflake()),
+            channel_name: thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(char::from)
+                .collect(),
+            guild_id: GuildId(tests::snowflake()),
+            guild_name: Some(
+                thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(10)
+                    .map(char::from)
+                    .collect(),
+            ),
+            added_by: UserId(tests::snowflake()),
+            suppress: Some(false),
+            added_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn create_test() {
+        let app = init().await;
+        let data = rng_add_channel();
+        let json_string = to_string(&data).unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(json_string))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            format!("/channel/{}", data.channel_id)
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        updated_at(&body);
+    }
+
+    #[tokio::test]
+    async fn create_invalid_fields_test() {
+        let app = init().await;
+        let data = Create {
+            channel_name: String::new(),
+            guild_id: GuildId(0),
+            ..rng_add_channel()
+        };
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(to_string(&data).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["data"],
+            json!(["channel_name: empty", "guild_id: must be positive"])
+        );
+    }
+
+    #[tokio::test]
+    async fn create_malformed_test() {
+        let app = init().await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"channel_id":"abc"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains("channel_id"), "{message}");
+        assert!(message.contains("expected i64"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn create_unknown_field_test() {
+        let app = init().await;
+        let data = rng_add_channel();
+        let mut body = serde_json::to_value(&data).unwrap();
+        let name = body
+            .as_object_mut()
+            .unwrap()
+            .remove("channel_name")
+            .unwrap();
+        body["channelname"] = name;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains("unknown field `channelname`"), "{message}");
+        assert_eq!(body["data"], json!(["channelname: unknown field"]));
+    }
+
+    #[tokio::test]
+    async fn create_bogus_snowflake_test() {
+        let mut app = init().await.into_service();
+        let future = (Utc::now().timestamp_millis() + 60_000 - DISCORD_EPOCH_MS) << 22;
+        for guild_id in [7, future] {
+            let data = Create {
+                guild_id: GuildId(guild_id),
+                ..rng_add_channel()
+            };
+            let request = Request::post("/channel")
+                .header("Content-Type", "application/json")
+                .body(Body::from(to_string(&data).unwrap()))
+                .unwrap();
+            let response = app.call(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{guild_id}");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], "validation_error");
+            assert!(body["message"].as_str().unwrap().starts_with("guild_id"));
+            let (status, _) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+    }
+
+    // The same check guards every route that creates a channel.
+    #[tokio::test]
+    async fn bogus_snowflake_other_routes_test() {
+        let mut app = init().await.into_service();
+        let data = Create {
+            guild_id: GuildId(7),
+            ..rng_add_channel()
+        };
+        let requests = [
+            ("POST", "/channel/sync".to_string(), json!([&data])),
+            (
+                "POST",
+                "/channel/full".to_string(),
+                json!({ "channel": &data, "anilist": [] }),
+            ),
+            (
+                "PUT",
+                format!("/channel/{}", data.channel_id),
+                json!({
+                    "channel_name": data.channel_name,
+                    "guild_id": 7,
+                    "added_by": data.added_by,
+                }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            let request = Request::builder()
+                .method(method)
+                .uri(&uri)
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.call(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert!(body["message"].as_str().unwrap().starts_with("guild_id"));
+        }
+        let (status, _) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn get_detail_test() {
+        let mut app = init()
+            .await
+            .route_layer(middleware::from_fn_with_state(
+                Arc::new(tests::pool()),
+                audit::record,
+            ))
+            .into_service();
+        let data = rng_add_channel();
+        let uri = format!("/channel/{}/detail", data.channel_id);
+
+        let (status, _) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        add_channel(&mut app, &data).await;
+        for guild_name in ["renamed", "renamed again"] {
+            let request = Request::patch(format!("/channel/{}", data.channel_id))
+                .header("Content-Type", "application/json")
+                .body(Body::from(json!({ "guild_name": guild_name }).to_string()))
+                .unwrap();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let (status, body) = get_json(&mut app, format!("{uri}?limit=2")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["channel"]["guild_name"], "renamed again");
+        let history = body["history"].as_array().unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|entry| entry["method"] == "PATCH"));
+        assert_eq!(history[0]["affected_id"], data.channel_id.0);
+
+        // The history outlives the channel.
+        let status = send(&mut app, "DELETE", format!("/channel/{}", data.channel_id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = get_json(&mut app, uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["channel"].is_null());
+        assert_eq!(body["history"].as_array().unwrap().len(), 4);
+        assert_eq!(body["history"][0]["method"], "DELETE");
+    }
+
+    #[tokio::test]
+    async fn create_without_guild_name_test() {
+        let mut app = init().await.into_service();
+        for guild_name in [None, Some(String::new())] {
+            let data = Create {
+                guild_name,
+                ..rng_add_channel()
+            };
+            add_channel(&mut app, &data).await;
+
+            let (status, body) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["guild_name"], UNKNOWN_GUILD_NAME);
+        }
+    }
+
+    #[tokio::test]
+    async fn create_with_added_at_test() {
+        let plain = init().await;
+        let added_at: DateTime<Utc> = "2020-01-02T03:04:05Z".parse().unwrap();
+        let data = Create {
+            added_at: Some(added_at),
+            ..rng_add_channel()
+        };
+
+        let request = Request::post("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(to_string(&data).unwrap()))
+            .unwrap();
+        let response = plain.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let mut app = plain
+            .layer(middleware::from_fn_with_state(true, scope_admin_fields))
+            .into_service();
+        add_channel(&mut app, &data).await;
+
+        let con = tests::pool().get().await.unwrap();
+        let stored: DateTime<Utc> = con
+            .query_one(
+                "SELECT added_at FROM channels WHERE channel_id = $1",
+                &[&data.channel_id],
+            )
+            .await
+            .unwrap()
+            .get("added_at");
+        assert_eq!(stored, added_at);
+    }
+
+    #[tokio::test]
+    async fn snowflakes_as_strings_test() {
+        let plain = init().await;
+        let mut app = plain
+            .clone()
+            .layer(middleware::from_fn_with_state(
+                true,
+                types::scope_snowflakes_as_strings,
+            ))
+            .into_service();
+        let data = rng_add_channel();
+        let payload = json!({
+            "channel_id": data.channel_id.to_string(),
+            "channel_name": data.channel_name,
+            "guild_id": data.guild_id.to_string(),
+            "added_by": data.added_by.to_string(),
+        });
+
+        let request = Request::post("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap();
+        let status = app.call(request).await.unwrap().status();
+        let (_, body) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["guild_id"], data.guild_id.to_string());
+        assert_eq!(body["added_by"], data.added_by.to_string());
+
+        let con = tests::pool().get().await.unwrap();
+        let row = con
+            .query_one(
+                "SELECT guild_id, added_by FROM channels WHERE channel_id = $1",
+                &[&data.channel_id],
+            )
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, GuildId>("guild_id"), data.guild_id);
+        assert_eq!(row.get::<_, UserId>("added_by"), data.added_by);
+
+        // An app without the setting reads the same channel as numbers.
+        let mut app = plain.into_service();
+        let (_, body) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+        assert_eq!(body["guild_id"], data.guild_id.0);
+    }
+
+    #[tokio::test]
+    async fn events_test() {
+        let mut app = init().await.into_service();
+        let request = Request::get("/channel/events")
+            .header(header::ACCEPT, "text/event-stream")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body();
+
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        // Other tests may publish too, so skip anything that isn't ours.
+        let expected = format!(
+            "event: create\ndata: {{\"operation\":\"create\",\"channel_id\":{}}}\n\n",
+            data.channel_id
+        );
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let frame = body.frame().await.unwrap().unwrap();
+                let Ok(chunk) = frame.into_data() else {
+                    continue;
+                };
+                if String::from_utf8_lossy(&chunk) == expected {
+                    break;
+                }
+            }
+        })
+        .await;
+        assert!(received.is_ok(), "no create event for {}", data.channel_id);
+    }
+
+    #[tokio::test]
+    async fn export_stream_test() {
+        let mut app = init().await.into_service();
+        let channels: Vec<Create> = (0..3).map(|_| rng_add_channel()).collect();
+        for data in &channels {
+            add_channel(&mut app, data).await;
+        }
+
+        let response = app
+            .call(Request::get("/channel/export").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], NDJSON);
+
+        // Other tests may add channels too, so only count ours.
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.ends_with('\n'));
+        let ours = body
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .filter(|line| {
+                channels
+                    .iter()
+                    .any(|data| line["channel_id"] == data.channel_id.0)
+            })
+            .count();
+        assert_eq!(ours, channels.len());
+    }
+
+    async fn register(app: &mut RouterIntoService<Body>, body: Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel/full")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn subscription(anilist_id: i64) -> Value {
+        json!({
+            "anilist_id": anilist_id,
+            "anilist_name": anilist_id.to_string(),
+            "site_url": format!("https://anilist.co/user/{anilist_id}/"),
+            "channel_id": 0,
+            "added_by": 1,
+        })
+    }
+
+    #[tokio::test]
+    async fn register_with_subscriptions_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+
+        let body = json!({ "channel": data, "anilist": [subscription(1), subscription(2)] });
+        let (status, body) = register(&mut app, body).await;
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["data"], json!(["2"]));
+        assert_eq!(subscriptions(data.channel_id).await, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn register_with_subscriptions_rollback_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+
+        let body = json!({ "channel": data, "anilist": [subscription(1), subscription(1)] });
+        let (status, body) = register(&mut app, body).await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "duplicate_subscription");
+        assert_eq!(stored_deleted_at(data.channel_id).await, None);
+        assert!(subscriptions(data.channel_id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn register_with_subscriptions_site_url_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let mut foreign = subscription(2);
+        foreign["site_url"] = json!("https://myanimelist.net/profile/2");
+
+        let body = json!({ "channel": data, "anilist": [subscription(1), foreign] });
+        let (status, body) = register(&mut app, body).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body["data"],
+            json!(["anilist[1].site_url: not an https://anilist.co URL"])
+        );
+        assert_eq!(stored_deleted_at(data.channel_id).await, None);
+    }
+
+    async fn post_sync(app: &mut RouterIntoService<Body>, channels: Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel/sync")
+            .header("Content-Type", "application/json")
+            .body(Body::from(channels.to_string()))
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn sync_test() {
+        let mut app = init().await.into_service();
+        let unchanged = rng_add_channel();
+        let renamed = rng_add_channel();
+        let renamed_id = renamed.channel_id;
+        add_channel(&mut app, &unchanged).await;
+        add_channel(&mut app, &renamed).await;
+
+        let channels = json!([
+            unchanged,
+            Create {
+                channel_name: "renamed".to_string(),
+                ..renamed
+            },
+            rng_add_channel(),
+            rng_add_channel(),
+        ]);
+        let (status, body) = post_sync(&mut app, channels).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"], json!(["2", "1"]));
+        let (_, body) = get_json(&mut app, format!("/channel/{renamed_id}")).await;
+        assert_eq!(body["channel_name"], "renamed");
+    }
+
+    #[tokio::test]
+    async fn sync_too_many_test() {
+        let mut app = init().await.into_service();
+        let channels: Vec<Create> = (0..=DEFAULT_MAX_BULK_ITEMS)
+            .map(|_| rng_add_channel())
+            .collect();
+
+        let (status, body) = post_sync(&mut app, json!(channels)).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["data"], json!(["channels: more than 1000 items"]));
+        let con = tests::pool().get().await.unwrap();
+        let count: i64 = con
+            .query_one("SELECT COUNT(*) FROM channels", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn sync_duplicate_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+
+        let (status, _) = post_sync(&mut app, json!([data, data])).await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn create_twice_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let json_string = to_string(&data).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json_string.clone()))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        updated_at(&body);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json_string.clone()))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "duplicate_channel");
+        assert_eq!(body["sqlstate"], "23505");
+    }
+
+    #[tokio::test]
+    async fn create_twice_ignore_test() {
+        let mut app = init_with_policy(DuplicatePolicy::Ignore)
+            .await
+            .into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let renamed = Create {
+            channel_name: "renamed".to_string(),
+            ..data
+        };
+        let request = Request::post("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(to_string(&renamed).unwrap()))
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"message": format!("{} already exists", renamed.channel_id)})
+        );
+
+        // The stored channel is left alone.
+        let (_, body) = get_json(&mut app, format!("/channel/{}", renamed.channel_id)).await;
+        assert_ne!(body["channel_name"], "renamed");
+    }
+
+    #[test]
+    fn duplicate_policy_test() {
+        assert_eq!("conflict".parse(), Ok(DuplicatePolicy::Conflict));
+        assert_eq!("ignore".parse(), Ok(DuplicatePolicy::Ignore));
+        assert!("skip".parse::<DuplicatePolicy>().is_err());
+    }
+
+    #[tokio::test]
+    async fn get_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let json_string = to_string(&data).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json_string.clone()))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        updated_at(&body);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri(format!("/channel/{}", data.channel_id))
+            .header("Content-Type", "application/json")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"channel_name": data.channel_name, "guild_id": data.guild_id, "guild_name": data.guild_name, "suppress": false, "added_by": data.added_by})
+        );
+    }
+
+    #[tokio::test]
+    async fn exists_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        for (channel_id, expected) in [
+            (data.channel_id, StatusCode::OK),
+            (ChannelId(tests::snowflake()), StatusCode::NOT_FOUND),
+        ] {
+            let request = Request::builder()
+                .method("HEAD")
+                .uri(format!("/channel/{channel_id}"))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.call(request).await.unwrap();
+
+            assert_eq!(response.status(), expected);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert!(body.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn get_fields_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let uri = format!("/channel/{}?fields=suppress", data.channel_id);
+        let (status, body) = get_json(&mut app, uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"suppress": false}));
+
+        let uri = format!("/channel/{}?fields=guild_id,channel_name", data.channel_id);
+        let (_, body) = get_json(&mut app, uri).await;
+        assert_eq!(
+            body,
+            json!({"channel_name": data.channel_name, "guild_id": data.guild_id})
+        );
+    }
+
+    #[tokio::test]
+    async fn get_unknown_fields_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let uri = format!("/channel/{}?fields=suppress,deleted_at", data.channel_id);
+        let (status, body) = get_json(&mut app, uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["data"], json!(["deleted_at: unknown field"]));
+    }
+
+    #[tokio::test]
+    async fn get_not_modified_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let json_string = to_string(&data).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json_string))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri(format!("/channel/{}", data.channel_id))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+
+        let request = Request::builder()
+            .method("GET")
+            .uri(format!("/channel/{}", data.channel_id))
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .body(Body::empty())
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let request = Request::builder()
+            .method("GET")
+            .uri(format!("/channel/{}", data.channel_id))
+            .header(header::IF_NONE_MATCH, "\"stale\"")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn get_invalid() {
+        let app = init().await;
+        let data = rng_add_channel();
+        let json_string = to_string(&data).unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/channel/{}", data.channel_id))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(json_string))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"message": format!("Could not find {}", data.channel_id), "code": "not_found"})
+        );
+    }
+
+    async fn put_channel(
+        app: &mut RouterIntoService<Body>,
+        uri: String,
+        body: &Replace,
+    ) -> Response {
+        let request = Request::builder()
+            .method("PUT")
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .body(Body::from(to_string(body).unwrap()))
+            .unwrap();
+
+        ServiceExt::<Request<Body>>::ready(app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap()
+    }
+
+    fn rng_replace(data: &Create) -> Replace {
+        Replace {
+            channel_name: data.channel_name.clone(),
+            guild_id: data.guild_id,
+            guild_name: data.guild_name.clone(),
+            added_by: data.added_by,
+            suppress: Some(true),
+        }
+    }
+
+    #[tokio::test]
+    async fn put_replace_create_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let uri = format!("/channel/{}", data.channel_id);
+
+        let response = put_channel(&mut app, uri.clone(), &rng_replace(&data)).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::LOCATION], uri);
+
+        let (_, body) = get_json(&mut app, uri).await;
+        assert_eq!(
+            body,
+            json!({"channel_name": data.channel_name, "guild_id": data.guild_id, "guild_name": data.guild_name, "suppress": true, "added_by": data.added_by})
+        );
+    }
+
+    #[tokio::test]
+    async fn put_replace_existing_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+        let uri = format!("/channel/{}", data.channel_id);
+
+        let replacement = Replace {
+            channel_name: "renamed".to_string(),
+            suppress: None,
+            ..rng_replace(&rng_add_channel())
+        };
+        let response = put_channel(&mut app, uri.clone(), &replacement).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (_, body) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(
+            body,
+            json!({"channel_name": "renamed", "guild_id": replacement.guild_id, "guild_name": replacement.guild_name, "suppress": false, "added_by": replacement.added_by})
+        );
+
+        // Replaying the same PUT is a no-op.
+        let response = put_channel(&mut app, uri, &replacement).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn update_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let json_string = to_string(&data).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json_string.clone()))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        updated_at(&body);
+
+        let request = Request::builder()
+            .method("PATCH")
+            .uri(format!("/channel/{}", data.channel_id))
+            .header("Content-Type", "application/json")
+            .body(Body::from("{\"suppress\": true}"))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        updated_at(&body);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri(format!("/channel/{}", data.channel_id))
+            .header("Content-Type", "application/json")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"channel_name": data.channel_name, "guild_id": data.guild_id, "guild_name": data.guild_name, "suppress": true, "added_by": data.added_by})
+        );
+    }
+
+    async fn merge_patch_request(
+        app: &mut RouterIntoService<Body>,
+        uri: &str,
+        body: Value,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method("PATCH")
+            .uri(uri)
+            .header("Content-Type", "application/merge-patch+json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn merge_patch_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+        let uri = format!("/channel/{}", data.channel_id);
+
+        // Set
+        let (status, _) = merge_patch_request(
+            &mut app,
+            &uri,
+            json!({ "suppress": true, "guild_name": "renamed" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(body["suppress"], true);
+        assert_eq!(body["guild_name"], "renamed");
+
+        // Omitted members are left alone
+        let (status, _) = merge_patch_request(&mut app, &uri, json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(body["suppress"], true);
+        assert_eq!(body["guild_name"], "renamed");
+
+        // Null resets, here only suppress
+        let (status, _) = merge_patch_request(&mut app, &uri, json!({ "suppress": null })).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(body["suppress"], false);
+        assert_eq!(body["guild_name"], "renamed");
+
+        let (status, _) = merge_patch_request(&mut app, &uri, json!({ "guild_name": null })).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(body["guild_name"], UNKNOWN_GUILD_NAME);
+    }
+
+    #[tokio::test]
+    async fn merge_patch_invalid_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+        let uri = format!("/channel/{}", data.channel_id);
+
+        let (status, body) =
+            merge_patch_request(&mut app, &uri, json!({ "channel_name": "renamed" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_error");
+        assert_eq!(body["data"], json!(["channel_name: unknown field"]));
+
+        for body in [json!({ "suppress": "yes" }), json!([{ "suppress": true }])] {
+            let (status, body) = merge_patch_request(&mut app, &uri, body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["code"], "validation_error");
+        }
+
+        let (_, body) = get_json(&mut app, uri).await;
+        assert_eq!(body["suppress"], false);
+    }
+
+    // Fields PATCH can't change are rejected rather than dropped, whichever
+    // content type the body was sent as.
+    #[tokio::test]
+    async fn update_unknown_field_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let request = Request::builder()
+            .method("PATCH")
+            .uri(format!("/channel/{}", data.channel_id))
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({ "suppress": true, "channel_name": "renamed" }).to_string(),
+            ))
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"], json!(["channel_name: unknown field"]));
+
+        let (_, body) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+        assert_eq!(body["suppress"], false);
+        assert_eq!(body["channel_name"], data.channel_name);
+    }
+
+    #[tokio::test]
+    async fn update_clear_guild_name_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+        let uri = format!("/channel/{}", data.channel_id);
+
+        for body in [
+            json!({ "guild_name": "" }),
+            json!({ "guild_name": "renamed" }),
+            json!({ "guild_name": null }),
+            json!({ "guild_name": "renamed" }),
+        ] {
+            let expected = match body["guild_name"].as_str() {
+                Some("renamed") => "renamed",
+                _ => UNKNOWN_GUILD_NAME,
+            };
+            let request = Request::builder()
+                .method("PATCH")
+                .uri(&uri)
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+
+            let response = ServiceExt::<Request<Body>>::ready(&mut app)
+                .await
+                .unwrap()
+                .call(request)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{body}");
+
+            let (_, body) = get_json(&mut app, uri.clone()).await;
+            assert_eq!(body["guild_name"], expected);
+        }
+
+        // Leaving guild_name out keeps whatever is stored.
+        let request = Request::builder()
+            .method("PATCH")
+            .uri(&uri)
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"suppress":true}"#))
+            .unwrap();
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let (_, body) = get_json(&mut app, uri).await;
+        assert_eq!(body["guild_name"], "renamed");
+    }
+
+    async fn patch_unmodified_since(
+        app: &mut RouterIntoService<Body>,
+        channel_id: ChannelId,
+        unmodified_since: &str,
+    ) -> StatusCode {
+        let request = Request::builder()
+            .method("PATCH")
+            .uri(format!("/channel/{channel_id}"))
+            .header("Content-Type", "application/json")
+            .header(header::IF_UNMODIFIED_SINCE, unmodified_since)
+            .body(Body::from("{\"suppress\": true}"))
+            .unwrap();
+
+        ServiceExt::<Request<Body>>::ready(app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn update_stale_test() {
+        let mut app = init().await.into_service();
+        let mut data = rng_add_channel();
+        data.suppress = Some(false);
+        add_channel(&mut app, &data).await;
+
+        // Another instance wrote the channel after this client last read it.
+        let stale = http_date(Utc::now() - chrono::Duration::hours(1));
+        let status = patch_unmodified_since(&mut app, data.channel_id, &stale).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let uri = format!("/channel/{}", data.channel_id);
+        let (_, body) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(body["suppress"], false);
+
+        let response = app
+            .call(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let last_modified = response.headers()[header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let status = patch_unmodified_since(&mut app, data.channel_id, &last_modified).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn put_replace_stale_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri(format!("/channel/{}", data.channel_id))
+            .header("Content-Type", "application/json")
+            .header(
+                header::IF_UNMODIFIED_SINCE,
+                http_date(Utc::now() - chrono::Duration::hours(1)),
+            )
+            .body(Body::from(to_string(&rng_replace(&data)).unwrap()))
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn update_missing_test() {
+        let app = init().await;
+        let channel_id = tests::snowflake();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri(format!("/channel/{channel_id}"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from("{\"suppress\": true}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"message": format!("Could not find {channel_id}"), "code": "not_found"})
+        );
+    }
+
+    #[tokio::test]
+    async fn update_bad_unmodified_since_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let status = patch_unmodified_since(&mut app, data.channel_id, "yesterday").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn touch_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let uri = format!("/channel/{}", data.channel_id);
+        let (_, body) = get_json(&mut app, uri.clone()).await;
+        assert_eq!(body.get("last_seen_at"), None);
+
+        let status = send(&mut app, "POST", format!("{uri}/touch")).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (_, body) = get_json(&mut app, uri).await;
+        let last_seen_at: DateTime<Utc> =
+            serde_json::from_value(body["last_seen_at"].clone()).unwrap();
+        assert!(Utc::now() - last_seen_at < chrono::Duration::minutes(1));
+    }
+
+    #[tokio::test]
+    async fn touch_missing_test() {
+        let mut app = init().await.into_service();
+        let uri = format!("/channel/{}/touch", tests::snowflake());
+
+        assert_eq!(send(&mut app, "POST", uri).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn toggle_suppress_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let json_string = to_string(&data).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json_string))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        for expected in [true, false] {
+            let request = Request::builder()
+                .method("POST")
+                .uri(format!("/channel/{}/suppress/toggle", data.channel_id))
+                .body(Body::empty())
+                .unwrap();
+
+            let response = ServiceExt::<Request<Body>>::ready(&mut app)
+                .await
+                .unwrap()
+                .call(request)
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            updated_at(&body);
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["suppress"], expected);
+        }
+    }
+
+    // Asserts the body of a write carries a parseable RFC 3339 updated_at.
+    fn updated_at(body: &[u8]) -> DateTime<Utc> {
+        let body: Value = serde_json::from_slice(body).unwrap();
+        let updated_at = body["updated_at"].as_str().expect("updated_at is missing");
+        DateTime::parse_from_rfc3339(updated_at).unwrap().into()
+    }
+
+    #[tokio::test]
+    async fn toggle_suppress_invalid() {
+        let app = init().await;
+        let channel_id = random::<i64>();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/channel/{channel_id}/suppress/toggle"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"message": format!("Could not find {channel_id}"), "code": "not_found"})
+        );
+    }
+
+    async fn put_owner(
+        app: &mut RouterIntoService<Body>,
+        channel_id: ChannelId,
+        added_by: UserId,
+    ) -> StatusCode {
+        let request = Request::builder()
+            .method("PUT")
+            .uri(format!("/channel/{channel_id}/owner"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(json!({ "added_by": added_by }).to_string()))
+            .unwrap();
+
+        ServiceExt::<Request<Body>>::ready(app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn transfer_owner_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+        let owner = UserId(tests::snowflake());
+
+        let status = put_owner(&mut app, data.channel_id, owner).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, body) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+        assert_eq!(body["added_by"], owner.0);
+    }
+
+    #[tokio::test]
+    async fn transfer_owner_invalid_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let status = put_owner(&mut app, data.channel_id, UserId(0)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let missing = ChannelId(tests::snowflake());
+        let status = put_owner(&mut app, missing, data.added_by).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    async fn add_channel(app: &mut RouterIntoService<Body>, data: &Create) {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/channel")
+            .header("Content-Type", "application/json")
+            .body(Body::from(to_string(data).unwrap()))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    async fn send(app: &mut RouterIntoService<Body>, method: &str, uri: String) -> StatusCode {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+
+        ServiceExt::<Request<Body>>::ready(app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    async fn stored_deleted_at(channel_id: ChannelId) -> Option<Option<DateTime<Utc>>> {
+        let con = tests::pool().get().await.unwrap();
+        con.query_opt(
+            "SELECT deleted_at FROM channels WHERE channel_id = $1",
+            &[&channel_id],
+        )
+        .await
+        .unwrap()
+        .map(|row| row.get("deleted_at"))
+    }
+
+    async fn get_json(app: &mut RouterIntoService<Body>, uri: String) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method("GET")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn set_added_at(channel_id: ChannelId, added_at: &str) {
+        let added_at = DateTime::parse_from_rfc3339(added_at).unwrap();
+        let con = tests::pool().get().await.unwrap();
+        con.execute(
+            "UPDATE channels SET added_at = $1 WHERE channel_id = $2",
+            &[&added_at, &channel_id],
+        )
+        .await
+        .unwrap();
+    }
+
+    fn ids(body: &Value) -> Vec<ChannelId> {
+        body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|channel| ChannelId(channel["channel_id"].as_i64().unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn list_range_test() {
+        let mut app = init().await.into_service();
+        let channels = [rng_add_channel(), rng_add_channel(), rng_add_channel()];
+        let dates = [
+            "2024-01-01T00:00:00Z",
+            "2024-02-01T00:00:00Z",
+            "2024-03-01T00:00:00Z",
+        ];
+        for (data, date) in channels.iter().zip(dates) {
+            add_channel(&mut app, data).await;
+            set_added_at(data.channel_id, date).await;
+        }
+
+        let uri = "/channel?from=2024-01-15T00:00:00Z&to=2024-02-15T00:00:00%2B00:00";
+        let (status, body) = get_json(&mut app, uri.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(ids(&body), [channels[1].channel_id]);
+    }
+
+    #[tokio::test]
+    async fn list_open_range_test() {
+        let mut app = init().await.into_service();
+        let old = rng_add_channel();
+        add_channel(&mut app, &old).await;
+        set_added_at(old.channel_id, "2020-01-01T00:00:00Z").await;
+        let new = rng_add_channel();
+        add_channel(&mut app, &new).await;
+
+        let uri = "/channel?from=2021-01-01T00:00:00Z";
+        let (status, body) = get_json(&mut app, uri.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(ids(&body), [new.channel_id]);
+
+        let (_, body) = get_json(&mut app, "/channel".to_string()).await;
+        assert_eq!(ids(&body), [old.channel_id, new.channel_id]);
+    }
+
+    #[tokio::test]
+    async fn list_cursor_test() {
+        let mut app = init().await.into_service();
+        let mut expected = Vec::new();
+        for day in 1..=5 {
+            let data = rng_add_channel();
+            add_channel(&mut app, &data).await;
+            set_added_at(data.channel_id, &format!("2024-01-0{day}T00:00:00.123456Z")).await;
+            expected.push(data.channel_id);
+        }
+
+        let mut seen = Vec::new();
+        let mut uri = "/channel?limit=2".to_string();
+        let mut pages = 0;
+        loop {
+            let (status, body) = get_json(&mut app, uri).await;
+            assert_eq!(status, StatusCode::OK);
+            seen.extend(ids(&body));
+            pages += 1;
+
+            match body["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("/channel?limit=2&cursor={cursor}"),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(seen, expected);
+    }
+
+    #[tokio::test]
+    async fn list_total_count_test() {
+        let mut app = init().await.into_service();
+        for _ in 0..3 {
+            add_channel(&mut app, &rng_add_channel()).await;
+        }
+
+        for uri in ["/channel", "/channel?limit=1", "/channel?limit=2&offset=2"] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.call(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[X_TOTAL_COUNT], "3", "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn list_limit_test() {
+        let mut app = init().await.into_service();
+        for _ in 0..3 {
+            add_channel(&mut app, &rng_add_channel()).await;
+        }
+
+        // Out of range limits are clamped rather than overflowing or
+        // reaching Postgres.
+        for (uri, len) in [
+            (format!("/channel?limit={}", i64::MAX), 3),
+            ("/channel?limit=-5".to_string(), 1),
+            ("/channel?limit=0".to_string(), 1),
+        ] {
+            let (status, body) = get_json(&mut app, uri.clone()).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+            assert_eq!(body["items"].as_array().unwrap().len(), len, "{uri}");
+        }
+
+        let (status, body) = get_json(&mut app, "/channel?offset=-1".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_error");
+    }
+
+    #[tokio::test]
+    async fn list_csv_test() {
+        let mut app = init().await.into_service();
+        let data = Create {
+            channel_name: "anime, news".to_string(),
+            ..rng_add_channel()
+        };
+        add_channel(&mut app, &data).await;
+        set_added_at(data.channel_id, "2024-01-01T00:00:00Z").await;
+
+        let request = Request::get("/channel")
+            .header(header::ACCEPT, "text/csv")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let mut lines = body.lines();
+        assert_eq!(
+            lines.next(),
+            Some("channel_id,channel_name,guild_id,guild_name,added_at,suppress")
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            format!(
+                "{},\"anime, news\",{},{},2024-01-01T00:00:00Z,false",
+                data.channel_id,
+                data.guild_id,
+                data.guild_name.unwrap()
+            )
+        );
+        assert_eq!(lines.next(), None);
+    }
+
+    #[tokio::test]
+    async fn list_wildcard_accept_is_json_test() {
+        let mut app = init().await.into_service();
+        let request = Request::get("/channel")
+            .header(header::ACCEPT, "*/*")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    }
+
+    #[tokio::test]
+    async fn list_suppress_filter_test() {
+        let mut app = init().await.into_service();
+        let mut suppressed = Vec::new();
+        for (day, suppress) in [(1, true), (2, false), (3, true)] {
+            let data = Create {
+                suppress: Some(suppress),
+                ..rng_add_channel()
+            };
+            add_channel(&mut app, &data).await;
+            set_added_at(data.channel_id, &format!("2024-01-0{day}T00:00:00Z")).await;
+            if suppress {
+                suppressed.push(data.channel_id);
+            }
+        }
+
+        let (_, body) = get_json(&mut app, "/channel?suppress=true".to_string()).await;
+        assert_eq!(ids(&body), suppressed);
+
+        let (_, body) = get_json(
+            &mut app,
+            "/channel?suppress=true&limit=1&offset=1".to_string(),
+        )
+        .await;
+        assert_eq!(ids(&body), suppressed[1..]);
+
+        let (_, body) = get_json(&mut app, "/channel?suppress=false".to_string()).await;
+        assert_eq!(ids(&body).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn list_last_modified_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let response = app
+            .call(Request::get("/channel").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+
+        let request = Request::get("/channel")
+            .header(header::IF_MODIFIED_SINCE, last_modified.clone())
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::LAST_MODIFIED], last_modified);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let request = Request::get("/channel")
+            .header(header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deleting the only channel empties the list, so it must not be 304.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let request = Request::builder()
+            .method("DELETE")
+            .uri(format!("/channel/{}", data.channel_id))
+            .body(Body::empty())
+            .unwrap();
+        app.call(request).await.unwrap();
+
+        let request = Request::get("/channel")
+            .header(header::IF_MODIFIED_SINCE, last_modified)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Restoring it and then deleting it for good both move the date on.
+        for (method, uri) in [
+            ("POST", format!("/channel/{}/restore", data.channel_id)),
+            ("DELETE", format!("/channel/{}?hard=true", data.channel_id)),
+        ] {
+            let response = app
+                .call(Request::get("/channel").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            assert!(
+                send(&mut app, method, uri.clone()).await.is_success(),
+                "{uri}"
+            );
+
+            let request = Request::get("/channel")
+                .header(header::IF_MODIFIED_SINCE, last_modified)
+                .body(Body::empty())
+                .unwrap();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn list_invalid_cursor_test() {
+        let mut app = init().await.into_service();
+
+        let (status, body) = get_json(&mut app, "/channel?cursor=nope".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_error");
+    }
+
+    #[tokio::test]
+    async fn list_malformed_date_test() {
+        let mut app = init().await.into_service();
+
+        let (status, body) = get_json(&mut app, "/channel?from=yesterday".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_error");
+        assert!(body["message"].as_str().unwrap().starts_with("from"));
+    }
+
+    #[tokio::test]
+    async fn invalid_path_test() {
+        let mut app = init().await.into_service();
+
+        let (status, body) = get_json(&mut app, "/channel/abc".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            json!({ "message": "invalid channel id", "code": "validation_error" })
+        );
+
+        let request = Request::builder()
+            .method("DELETE")
+            .uri("/guild/abc/channel")
+            .body(Body::empty())
+            .unwrap();
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "invalid guild id");
+    }
+
+    #[tokio::test]
+    async fn search_test() {
+        let mut app = init().await.into_service();
+        for name in ["anime-news", "ANIME_chat", "safe-chat"] {
+            let data = Create {
+                channel_name: name.to_string(),
+                ..rng_add_channel()
+            };
+            add_channel(&mut app, &data).await;
+        }
+
+        let (status, body) = get_json(&mut app, "/channel/search?q=anime".to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        let names: Vec<&str> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|channel| channel["channel_name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["ANIME_chat", "anime-news"]);
+        assert!(body[0]["channel_id"].is_i64());
+
+        let (_, body) = get_json(&mut app, "/channel/search?q=e_c".to_string()).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        let (_, body) = get_json(&mut app, "/channel/search?q=anime&limit=1".to_string()).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn search_limit_test() {
+        let mut app = init().await.into_service();
+        for name in ["anime-news", "anime-chat"] {
+            let data = Create {
+                channel_name: name.to_string(),
+                ..rng_add_channel()
+            };
+            add_channel(&mut app, &data).await;
+        }
+
+        // Out of range limits are clamped rather than passed on to Postgres,
+        // which would reject a negative one.
+        for (limit, expected) in [(-1, 1), (0, 1), (i64::MAX, 2)] {
+            let uri = format!("/channel/search?q=anime&limit={limit}");
+            let (status, body) = get_json(&mut app, uri).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body.as_array().unwrap().len(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn search_empty_query_test() {
+        let mut app = init().await.into_service();
+
+        let (status, _) = get_json(&mut app, "/channel/search?q=".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn autocomplete_test() {
+        let mut app = init().await.into_service();
+        for name in ["foobar", "barfoo", "Football"] {
+            let data = Create {
+                channel_name: name.to_string(),
+                ..rng_add_channel()
+            };
+            add_channel(&mut app, &data).await;
+        }
+
+        let (status, body) = get_json(&mut app, "/channel/autocomplete?q=fo".to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        let names: Vec<&str> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|channel| channel["channel_name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["foobar", "Football"]);
+        assert_eq!(body[0].as_object().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn recent_test() {
+        let mut app = init().await.into_service();
+        let channels = [rng_add_channel(), rng_add_channel(), rng_add_channel()];
+        for (data, added_at) in channels.iter().zip([
+            "2024-01-02T00:00:00Z",
+            "2024-01-03T00:00:00Z",
+            "2024-01-01T00:00:00Z",
+        ]) {
+            add_channel(&mut app, data).await;
+            set_added_at(data.channel_id, added_at).await;
+        }
+
+        let (status, body) = get_json(&mut app, "/channel/recent?limit=2".to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!([
+                {
+                    "channel_id": channels[1].channel_id,
+                    "channel_name": channels[1].channel_name,
+                    "guild_name": channels[1].guild_name,
+                    "added_at": "2024-01-03T00:00:00Z",
+                },
+                {
+                    "channel_id": channels[0].channel_id,
+                    "channel_name": channels[0].channel_name,
+                    "guild_name": channels[0].guild_name,
+                    "added_at": "2024-01-02T00:00:00Z",
+                },
+            ])
+        );
+
+        let (_, body) = get_json(&mut app, "/channel/recent?limit=1000".to_string()).await;
+        assert_eq!(body.as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn most_subscribed_test() {
+        let mut app = init().await.into_service();
+        let channels = [rng_add_channel(), rng_add_channel(), rng_add_channel()];
+        for data in &channels {
+            add_channel(&mut app, data).await;
+        }
+        for anilist_id in 1..=3 {
+            insert_subscription(anilist_id, channels[1].channel_id).await;
+        }
+        insert_subscription(1, channels[2].channel_id).await;
+
+        let (status, body) = get_json(&mut app, "/channel/top".to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!([
+                {
+                    "channel_id": channels[1].channel_id,
+                    "channel_name": channels[1].channel_name,
+                    "subscription_count": 3,
+                },
+                {
+                    "channel_id": channels[2].channel_id,
+                    "channel_name": channels[2].channel_name,
+                    "subscription_count": 1,
+                },
+                {
+                    "channel_id": channels[0].channel_id,
+                    "channel_name": channels[0].channel_name,
+                    "subscription_count": 0,
+                },
+            ])
+        );
+
+        let (_, body) = get_json(&mut app, "/channel/top?min=1".to_string()).await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        let (_, body) = get_json(&mut app, "/channel/top?limit=1".to_string()).await;
+        assert_eq!(body[0]["channel_id"], json!(channels[1].channel_id));
+        assert_eq!(body.as_array().unwrap().len(), 1);
     }
 
-    fn rng_add_channel() -> Create {
-        Create {
-            channel_id: random::<i64>(),
-            channel_name: thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(10)
-                .map(char::from)
-                .collect(),
-            guild_id: random::<i64>(),
-            guild_name: thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(10)
-                .map(char::from)
-                .collect(),
-            added_by: random::<i64>(),
+    #[test]
+    fn channel_columns_test() {
+        let data = Data {
+            channel_id: Some(ChannelId(1)),
+            channel_name: Some(String::new()),
+            guild_id: Some(GuildId(1)),
+            guild_name: Some(String::new()),
             suppress: Some(false),
+            added_at: Some(Utc::now()),
+            added_by: Some(UserId(1)),
+            last_seen_at: Some(Utc::now()),
+        };
+        let value = serde_json::to_value(&data).unwrap();
+        let fields: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut columns: Vec<&str> = channel_columns!().split(", ").collect();
+        columns.sort_unstable();
+
+        assert_eq!(fields, columns);
+    }
+
+    #[tokio::test]
+    async fn search_escapes_wildcards_test() {
+        assert_eq!(escape_like("100%_off\\"), "100\\%\\_off\\\\");
+    }
+
+    #[tokio::test]
+    async fn set_guild_suppress_test() {
+        let mut app = init().await.into_service();
+        let guild_id = GuildId(tests::snowflake());
+        let channels = [
+            Create {
+                guild_id,
+                ..rng_add_channel()
+            },
+            Create {
+                guild_id,
+                ..rng_add_channel()
+            },
+        ];
+        for data in &channels {
+            add_channel(&mut app, data).await;
+        }
+        let other = rng_add_channel();
+        add_channel(&mut app, &other).await;
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri(format!("/guild/{guild_id}/suppress"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"suppress":true}"#))
+            .unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let mut ids: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|id| id.as_str().unwrap())
+            .collect();
+        ids.sort();
+        let mut expected = [
+            channels[0].channel_id.to_string(),
+            channels[1].channel_id.to_string(),
+        ];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        for data in &channels {
+            let (_, body) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+            assert_eq!(body["suppress"], true);
         }
+        let (_, body) = get_json(&mut app, format!("/channel/{}", other.channel_id)).await;
+        assert_eq!(body["suppress"], false);
     }
 
     #[tokio::test]
-    async fn create_test() {
+    async fn set_guild_suppress_empty_test() {
         let app = init().await;
-        let data = rng_add_channel();
-        let json_string = to_string(&data).unwrap();
 
         let response = app
             .oneshot(
                 Request::builder()
-                    .method("POST")
-                    .uri("/channel")
+                    .method("PUT")
+                    .uri("/guild/1/suppress")
                     .header("Content-Type", "application/json")
-                    .body(Body::from(json_string))
+                    .body(Body::from(r#"{"suppress":false}"#))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert!(body.is_empty());
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!({ "message": "Updated 0 channels", "data": [] }));
     }
 
     #[tokio::test]
-    async fn create_twice_test() {
+    async fn set_suppress_many_test() {
         let mut app = init().await.into_service();
-        let data = rng_add_channel();
-        let json_string = to_string(&data).unwrap();
+        let channels = [rng_add_channel(), rng_add_channel()];
+        for data in &channels {
+            add_channel(&mut app, data).await;
+        }
+        let other = rng_add_channel();
+        add_channel(&mut app, &other).await;
+
+        let missing = ChannelId(tests::snowflake());
+        let payload = json!({
+            "channel_ids": [channels[0].channel_id, missing, channels[1].channel_id],
+            "suppress": true,
+        });
         let request = Request::builder()
             .method("POST")
-            .uri("/channel")
+            .uri("/channel/suppress")
             .header("Content-Type", "application/json")
-            .body(Body::from(json_string.clone()))
+            .body(Body::from(payload.to_string()))
             .unwrap();
 
         let response = ServiceExt::<Request<Body>>::ready(&mut app)
@@ -290,17 +4528,194 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert!(body.is_empty());
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "Updated 2 channels");
+        let mut ids: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|id| id.as_str().unwrap())
+            .collect();
+        ids.sort();
+        let mut expected = [
+            channels[0].channel_id.to_string(),
+            channels[1].channel_id.to_string(),
+        ];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        for data in &channels {
+            let (_, body) = get_json(&mut app, format!("/channel/{}", data.channel_id)).await;
+            assert_eq!(body["suppress"], true);
+        }
+        let (_, body) = get_json(&mut app, format!("/channel/{}", other.channel_id)).await;
+        assert_eq!(body["suppress"], false);
+    }
+
+    async fn insert_subscription(anilist_id: i64, channel_id: ChannelId) {
+        let con = tests::pool().get().await.unwrap();
+        con.execute(
+            "INSERT INTO anilist VALUES ($1, 'user', 'https://anilist.co/user/1/', $2, NOW(), 1)",
+            &[&anilist_id, &channel_id],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn subscriptions(channel_id: ChannelId) -> Vec<i64> {
+        let con = tests::pool().get().await.unwrap();
+        con.query(
+            "SELECT anilist_id FROM anilist WHERE channel_id = $1 ORDER BY anilist_id",
+            &[&channel_id],
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get("anilist_id"))
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn get_full_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+        insert_subscription(1, data.channel_id).await;
+        insert_subscription(2, data.channel_id).await;
+
+        let uri = format!("/channel/{}/full", data.channel_id);
+        let (status, body) = get_json(&mut app, uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["channel"]["channel_id"], data.channel_id.0);
+        assert_eq!(body["channel"]["channel_name"], data.channel_name);
+        let anilist = body["anilist"].as_array().unwrap();
+        assert_eq!(anilist.len(), 2);
+        for (user, anilist_id) in anilist.iter().zip([1, 2]) {
+            assert_eq!(user["anilist_id"], anilist_id);
+            assert_eq!(user["channel_id"], data.channel_id.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn get_full_without_subscriptions_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let uri = format!("/channel/{}/full", data.channel_id);
+        let (status, body) = get_json(&mut app, uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["anilist"], json!([]));
+
+        let missing = tests::snowflake();
+        let (status, body) = get_json(&mut app, format!("/channel/{missing}/full")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn move_subscriptions_test() {
+        let mut app = init().await.into_service();
+        let from = rng_add_channel();
+        let to = rng_add_channel();
+        add_channel(&mut app, &from).await;
+        add_channel(&mut app, &to).await;
+        insert_subscription(1, from.channel_id).await;
+        insert_subscription(2, from.channel_id).await;
+
+        let uri = format!("/channel/{}/move/{}", from.channel_id, to.channel_id);
+        assert_eq!(send(&mut app, "POST", uri).await, StatusCode::OK);
+        assert!(subscriptions(from.channel_id).await.is_empty());
+        assert_eq!(subscriptions(to.channel_id).await, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn move_subscriptions_missing_test() {
+        let mut app = init().await.into_service();
+        let from = rng_add_channel();
+        add_channel(&mut app, &from).await;
+        insert_subscription(1, from.channel_id).await;
+
+        let missing = tests::snowflake();
+        let uri = format!("/channel/{}/move/{missing}", from.channel_id);
+        assert_eq!(send(&mut app, "POST", uri).await, StatusCode::NOT_FOUND);
+        assert_eq!(subscriptions(from.channel_id).await, [1]);
+    }
+
+    #[tokio::test]
+    async fn move_subscriptions_to_itself_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+        insert_subscription(1, data.channel_id).await;
+
+        let uri = format!("/channel/{}/move/{}", data.channel_id, data.channel_id);
+        assert_eq!(send(&mut app, "POST", uri).await, StatusCode::BAD_REQUEST);
+        assert_eq!(subscriptions(data.channel_id).await, [1]);
+    }
+
+    #[tokio::test]
+    async fn soft_delete_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let uri = format!("/channel/{}", data.channel_id);
+        assert_eq!(send(&mut app, "DELETE", uri.clone()).await, StatusCode::OK);
+        assert_eq!(send(&mut app, "GET", uri).await, StatusCode::NOT_FOUND);
+
+        let deleted_at = stored_deleted_at(data.channel_id).await;
+        assert!(matches!(deleted_at, Some(Some(_))));
+    }
+
+    #[tokio::test]
+    async fn hard_delete_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        let body = json!({ "channel": data, "anilist": [subscription(1)] });
+        let (status, _) = register(&mut app, body).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let uri = format!("/channel/{}?hard=true", data.channel_id);
+        assert_eq!(send(&mut app, "DELETE", uri).await, StatusCode::OK);
+        assert_eq!(stored_deleted_at(data.channel_id).await, None);
+        assert!(subscriptions(data.channel_id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn idempotent_delete_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let uri = format!("/channel/{}?idempotent=true", data.channel_id);
+        for _ in 0..2 {
+            assert_eq!(
+                send(&mut app, "DELETE", uri.clone()).await,
+                StatusCode::NO_CONTENT
+            );
+        }
+
+        let missing = tests::snowflake();
+        let uri = format!("/channel/{missing}?idempotent=true&hard=true");
+        assert_eq!(send(&mut app, "DELETE", uri).await, StatusCode::NO_CONTENT);
+        let uri = format!("/channel/{missing}");
+        assert_eq!(send(&mut app, "DELETE", uri).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_returns_row_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
 
         let request = Request::builder()
-            .method("POST")
-            .uri("/channel")
-            .header("Content-Type", "application/json")
-            .body(Body::from(json_string.clone()))
+            .method("DELETE")
+            .uri(format!("/channel/{}?hard=true", data.channel_id))
+            .body(Body::empty())
             .unwrap();
-
         let response = ServiceExt::<Request<Body>>::ready(&mut app)
             .await
             .unwrap()
@@ -308,21 +4723,42 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert!(!body.is_empty());
+        let mut body: Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["added_at"].is_string());
+        body.as_object_mut().unwrap().remove("added_at");
+        assert_eq!(
+            body,
+            json!({
+                "channel_id": data.channel_id,
+                "channel_name": data.channel_name,
+                "guild_id": data.guild_id,
+                "guild_name": data.guild_name,
+                "suppress": false,
+                "added_by": data.added_by
+            })
+        );
+
+        let uri = format!("/channel/{}", data.channel_id);
+        assert_eq!(send(&mut app, "DELETE", uri).await, StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn get_test() {
+    async fn get_many_test() {
         let mut app = init().await.into_service();
-        let data = rng_add_channel();
-        let json_string = to_string(&data).unwrap();
+        let channels = [rng_add_channel(), rng_add_channel()];
+        for data in &channels {
+            add_channel(&mut app, data).await;
+        }
+
+        let missing = ChannelId(tests::snowflake());
+        let ids = [channels[0].channel_id, missing, channels[1].channel_id];
         let request = Request::builder()
             .method("POST")
-            .uri("/channel")
+            .uri("/channel/batch-get")
             .header("Content-Type", "application/json")
-            .body(Body::from(json_string.clone()))
+            .body(Body::from(to_string(&ids).unwrap()))
             .unwrap();
 
         let response = ServiceExt::<Request<Body>>::ready(&mut app)
@@ -332,15 +4768,38 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert!(body.is_empty());
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let mut found: Vec<ChannelId> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|channel| ChannelId(channel["channel_id"].as_i64().unwrap()))
+            .collect();
+        found.sort();
+
+        let mut expected = vec![channels[0].channel_id, channels[1].channel_id];
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[tokio::test]
+    async fn delete_bulk_test() {
+        let mut app = init().await.into_service();
+        let channels = [rng_add_channel(), rng_add_channel(), rng_add_channel()];
+        for data in &channels {
+            let body = json!({ "channel": data, "anilist": [subscription(1)] });
+            let (status, _) = register(&mut app, body).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
 
+        let ids = [channels[0].channel_id, channels[1].channel_id];
         let request = Request::builder()
-            .method("GET")
-            .uri(format!("/channel/{}", data.channel_id))
+            .method("POST")
+            .uri("/channel/bulk-delete?hard=true")
             .header("Content-Type", "application/json")
-            .body(Body::empty())
+            .body(Body::from(to_string(&ids).unwrap()))
             .unwrap();
 
         let response = ServiceExt::<Request<Body>>::ready(&mut app)
@@ -355,47 +4814,30 @@ mod tests {
         let body: Value = serde_json::from_slice(&body).unwrap();
         assert_eq!(
             body,
-            json!({"channel_name": data.channel_name, "guild_id": data.guild_id, "guild_name": data.guild_name, "suppress": false})
+            json!({ "message": "Deleted 2 channels", "data": ["2"] })
         );
-    }
-
-    #[tokio::test]
-    async fn get_invalid() {
-        let app = init().await;
-        let data = rng_add_channel();
-        let json_string = to_string(&data).unwrap();
-
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("GET")
-                    .uri(format!("/channel/{}", data.channel_id))
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(json_string))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
-        let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: Value = serde_json::from_slice(&body).unwrap();
-        assert_eq!(
-            body,
-            json!({"message": format!("Could not find {}", data.channel_id)})
-        );
+        assert_eq!(stored_deleted_at(channels[0].channel_id).await, None);
+        assert_eq!(stored_deleted_at(channels[1].channel_id).await, None);
+        assert_eq!(stored_deleted_at(channels[2].channel_id).await, Some(None));
+        assert!(subscriptions(channels[0].channel_id).await.is_empty());
+        assert_eq!(subscriptions(channels[2].channel_id).await, [1]);
     }
 
     #[tokio::test]
-    async fn update_test() {
+    async fn delete_bulk_dry_run_test() {
         let mut app = init().await.into_service();
-        let data = rng_add_channel();
-        let json_string = to_string(&data).unwrap();
+        let channels = [rng_add_channel(), rng_add_channel()];
+        for data in &channels {
+            add_channel(&mut app, data).await;
+        }
+
+        let ids = [channels[0].channel_id, channels[1].channel_id, ChannelId(1)];
         let request = Request::builder()
             .method("POST")
-            .uri("/channel")
+            .uri("/channel/bulk-delete?hard=true&dry_run=true")
             .header("Content-Type", "application/json")
-            .body(Body::from(json_string.clone()))
+            .body(Body::from(to_string(&ids).unwrap()))
             .unwrap();
 
         let response = ServiceExt::<Request<Body>>::ready(&mut app)
@@ -405,32 +4847,92 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert!(body.is_empty());
-        
-        let request = Request::builder()
-            .method("PUT")
-            .uri(format!("/channel/{}", data.channel_id))
-            .header("Content-Type", "application/json")
-            .body(Body::from("{\"suppress\": true}"))
-            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "message": "dry run: 2 rows would be deleted", "data": ["2"] })
+        );
 
-        let response = ServiceExt::<Request<Body>>::ready(&mut app)
-            .await
-            .unwrap()
-            .call(request)
-            .await
+        for data in &channels {
+            assert_eq!(stored_deleted_at(data.channel_id).await, Some(None));
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_by_guild_dry_run_test() {
+        let mut app = init().await.into_service();
+        let guild_id = GuildId(tests::snowflake());
+        let data = Create {
+            guild_id,
+            ..rng_add_channel()
+        };
+        add_channel(&mut app, &data).await;
+
+        let request = Request::builder()
+            .method("DELETE")
+            .uri(format!("/guild/{guild_id}/channel?dry_run=true"))
+            .body(Body::empty())
             .unwrap();
+        let response = app.call(request).await.unwrap();
 
         assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert!(body.is_empty());
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "dry run: 1 rows would be deleted");
+        assert_eq!(stored_deleted_at(data.channel_id).await, Some(None));
+    }
+
+    #[tokio::test]
+    async fn delete_by_guild_test() {
+        let mut app = init().await.into_service();
+        let guild_id = GuildId(tests::snowflake());
+        let channels = [
+            Create {
+                guild_id,
+                ..rng_add_channel()
+            },
+            Create {
+                guild_id,
+                ..rng_add_channel()
+            },
+        ];
+        for data in &channels {
+            add_channel(&mut app, data).await;
+        }
+        let other = rng_add_channel();
+        add_channel(&mut app, &other).await;
+
+        let con = tests::pool().get().await.unwrap();
+        con.execute(
+            "INSERT INTO anilist VALUES (1, 'user', 'https://anilist.co/user/1/', $1, NOW(), 1)",
+            &[&channels[0].channel_id],
+        )
+        .await
+        .unwrap();
+
+        for uri in [
+            format!("/guild/{guild_id}/channel"),
+            format!("/guild/{guild_id}/channel?confirm={}", other.guild_id),
+        ] {
+            let request = Request::builder()
+                .method("DELETE")
+                .uri(&uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = app.call(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], "confirmation_required");
+            assert_eq!(stored_deleted_at(channels[0].channel_id).await, Some(None));
+        }
 
         let request = Request::builder()
-            .method("GET")
-            .uri(format!("/channel/{}", data.channel_id))
-            .header("Content-Type", "application/json")
+            .method("DELETE")
+            .uri(format!("/guild/{guild_id}/channel?confirm={guild_id}"))
             .body(Body::empty())
             .unwrap();
 
@@ -446,7 +4948,46 @@ mod tests {
         let body: Value = serde_json::from_slice(&body).unwrap();
         assert_eq!(
             body,
-            json!({"channel_name": data.channel_name, "guild_id": data.guild_id, "guild_name": data.guild_name, "suppress": true})
+            json!({ "message": "Deleted 2 channels", "data": ["2"] })
+        );
+
+        let remaining = con.query("SELECT 1 FROM anilist", &[]).await.unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(stored_deleted_at(channels[0].channel_id).await, None);
+        assert_eq!(stored_deleted_at(channels[1].channel_id).await, None);
+        assert_eq!(stored_deleted_at(other.channel_id).await, Some(None));
+    }
+
+    #[tokio::test]
+    async fn restore_test() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let uri = format!("/channel/{}", data.channel_id);
+        let restore_uri = format!("/channel/{}/restore", data.channel_id);
+        assert_eq!(send(&mut app, "DELETE", uri.clone()).await, StatusCode::OK);
+        assert_eq!(send(&mut app, "POST", restore_uri).await, StatusCode::OK);
+        assert_eq!(send(&mut app, "GET", uri).await, StatusCode::OK);
+        assert_eq!(stored_deleted_at(data.channel_id).await, Some(None));
+    }
+
+    #[tokio::test]
+    async fn restore_invalid() {
+        let mut app = init().await.into_service();
+        let data = rng_add_channel();
+        add_channel(&mut app, &data).await;
+
+        let restore_uri = format!("/channel/{}/restore", data.channel_id);
+        assert_eq!(
+            send(&mut app, "POST", restore_uri).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let missing_uri = format!("/channel/{}/restore", random::<i64>());
+        assert_eq!(
+            send(&mut app, "POST", missing_uri).await,
+            StatusCode::NOT_FOUND
         );
     }
 }
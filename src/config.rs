@@ -0,0 +1,117 @@
+use crate::{
+    admin::PurgeRetention,
+    channel::DuplicatePolicy,
+    util::{
+        body_limit::DEFAULT_MAX_BODY_BYTES,
+        db::{Recycling, Schema},
+        logging::LogFormat,
+        tls::SslMode,
+        validation::DEFAULT_MAX_BULK_ITEMS,
+    },
+};
+use std::{env, fmt::Debug, path::PathBuf, str::FromStr, time::Duration};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+pub struct Config {
+    pub allowed_origins: Option<String>,
+    pub api_key: String,
+    pub rate_limit_rps: u64,
+    pub max_body_bytes: usize,
+    pub max_bulk_items: usize,
+    pub pool_max_size: usize,
+    pub pool_wait_timeout: Duration,
+    pub db_sslmode: SslMode,
+    pub db_ca_cert: Option<PathBuf>,
+    pub db_schema: Schema,
+    pub statement_cache: bool,
+    pub db_recycling: Recycling,
+    pub log_format: LogFormat,
+    pub enable_admin_endpoints: bool,
+    pub duplicate_policy: DuplicatePolicy,
+    pub purge_retention: PurgeRetention,
+    pub wrap_responses: bool,
+    pub snowflakes_as_strings: bool,
+    pub log_sql: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            allowed_origins: None,
+            api_key: String::new(),
+            rate_limit_rps: 20,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_bulk_items: DEFAULT_MAX_BULK_ITEMS,
+            pool_max_size: 16,
+            pool_wait_timeout: Duration::from_secs(5),
+            db_sslmode: SslMode::Disable,
+            db_ca_cert: None,
+            db_schema: Schema::default(),
+            statement_cache: true,
+            db_recycling: Recycling::default(),
+            log_format: LogFormat::Pretty,
+            enable_admin_endpoints: false,
+            duplicate_policy: DuplicatePolicy::default(),
+            purge_retention: PurgeRetention::default(),
+            wrap_responses: false,
+            snowflakes_as_strings: false,
+            log_sql: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let default = Config::default();
+
+        Config {
+            allowed_origins: env::var("ALLOWED_ORIGINS").ok(),
+            api_key: env::var("API_KEY").expect("API_KEY ENV is missing"),
+            rate_limit_rps: parse_env("RATE_LIMIT_RPS", default.rate_limit_rps),
+            max_body_bytes: parse_env("MAX_BODY_BYTES", default.max_body_bytes),
+            max_bulk_items: parse_env("MAX_BULK_ITEMS", default.max_bulk_items),
+            pool_max_size: parse_env("POOL_MAX_SIZE", default.pool_max_size),
+            pool_wait_timeout: Duration::from_secs(parse_env(
+                "POOL_WAIT_TIMEOUT_SECS",
+                default.pool_wait_timeout.as_secs(),
+            )),
+            db_sslmode: parse_env("DB_SSLMODE", default.db_sslmode),
+            db_ca_cert: env::var("DB_CA_CERT").ok().map(PathBuf::from),
+            db_schema: parse_env("DB_SCHEMA", default.db_schema),
+            statement_cache: parse_env("STATEMENT_CACHE", default.statement_cache),
+            db_recycling: parse_env("DB_RECYCLING", default.db_recycling),
+            log_format: parse_env("LOG_FORMAT", default.log_format),
+            enable_admin_endpoints: parse_env(
+                "ENABLE_ADMIN_ENDPOINTS",
+                default.enable_admin_endpoints,
+            ),
+            duplicate_policy: parse_env("DUPLICATE_POLICY", default.duplicate_policy),
+            purge_retention: PurgeRetention(Duration::from_secs(
+                parse_env(
+                    "PURGE_RETENTION_DAYS",
+                    default.purge_retention.0.as_secs() / SECS_PER_DAY,
+                ) * SECS_PER_DAY,
+            )),
+            wrap_responses: parse_env("WRAP_RESPONSES", default.wrap_responses),
+            snowflakes_as_strings: parse_env(
+                "SNOWFLAKES_AS_STRINGS",
+                default.snowflakes_as_strings,
+            ),
+            log_sql: parse_env("LOG_SQL", default.log_sql),
+        }
+    }
+}
+
+fn parse_env<T>(name: &str, default: T) -> T
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|err| panic!("{name} ENV is invalid: {err:?}")),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,869 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::{
+    anilist::{self, UserData},
+    channel::guild_name_or_unknown,
+    types::{ChannelId, GuildId, UserId},
+    util::{
+        db::{execute, query, with_retry, with_transaction, Replica},
+        error_handling::{internal_error, pool_error},
+        events::{self, Operation},
+        json::ValidatedJson,
+        path::ValidatedPath,
+        validation::{validate_snowflake, FieldErrors},
+    },
+    Message,
+};
+
+// Discord's own limit on guild names.
+const GUILD_NAME_MAX_CHARS: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GuildSummary {
+    guild_id: GuildId,
+    guild_name: String,
+    channel_count: i64,
+}
+
+// Applied to channels added to the guild without an explicit value.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GuildDefaults {
+    suppress: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GuildName {
+    guild_name: String,
+}
+
+// guild_id is left out, it comes from the path on both ends.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExportedChannel {
+    channel_id: ChannelId,
+    channel_name: String,
+    guild_name: String,
+    suppress: bool,
+    /// Defaults to now on import. Only accepted with ENABLE_ADMIN_ENDPOINTS=true,
+    /// like added_at on POST /channel
+    added_at: Option<DateTime<Utc>>,
+    added_by: UserId,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GuildExport {
+    channels: Vec<ExportedChannel>,
+    anilist: Vec<UserData>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/guild",
+    tag = "guilds",
+    responses(
+        (status = 200, description = "Guilds with at least one channel, largest first", body = [GuildSummary]),
+    )
+)]
+pub async fn list(
+    State(Replica(pool)): State<Replica>,
+) -> Result<Json<Vec<GuildSummary>>, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(
+        &con,
+        "SELECT guild_id, MAX(guild_name) AS guild_name, COUNT(*) AS channel_count FROM channels WHERE deleted_at IS NULL GROUP BY guild_id ORDER BY channel_count DESC",
+        &[],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let guilds = result
+        .iter()
+        .map(|row| GuildSummary {
+            guild_id: row.get("guild_id"),
+            guild_name: row.get("guild_name"),
+            channel_count: row.get("channel_count"),
+        })
+        .collect();
+
+    Ok(Json(guilds))
+}
+
+#[utoipa::path(
+    post,
+    path = "/guild/summaries",
+    tag = "guilds",
+    request_body = Vec<GuildId>,
+    responses(
+        (status = 200, description = "Summaries of the requested guilds, ones without channels are omitted", body = [GuildSummary]),
+        (status = 400, description = "Malformed body", body = Message),
+        (status = 422, description = "Too many guild IDs", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn summaries(
+    State(Replica(pool)): State<Replica>,
+    ValidatedJson(guild_ids): ValidatedJson<Vec<GuildId>>,
+) -> Result<Json<Vec<GuildSummary>>, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .max_items("guild_ids", guild_ids.len())
+        .finish()?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(
+        &con,
+        "SELECT guild_id, MAX(guild_name) AS guild_name, COUNT(*) AS channel_count FROM channels WHERE guild_id = ANY($1) AND deleted_at IS NULL GROUP BY guild_id ORDER BY guild_id",
+        &[&guild_ids],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let guilds = result
+        .iter()
+        .map(|row| GuildSummary {
+            guild_id: row.get("guild_id"),
+            guild_name: row.get("guild_name"),
+            channel_count: row.get("channel_count"),
+        })
+        .collect();
+
+    Ok(Json(guilds))
+}
+
+// guild_name is copied onto every channel row, so a rename has to touch all
+// of them, soft-deleted ones included so a restore doesn't bring back the
+// old name.
+#[utoipa::path(
+    put,
+    path = "/guild/{guildid}/name",
+    tag = "guilds",
+    params(("guildid" = i64, Path, description = "Discord guild ID")),
+    request_body = GuildName,
+    responses(
+        (status = 200, description = "IDs of the renamed channels, in data", body = Message),
+        (status = 422, description = "Empty or overlong guild_name", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn rename(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(guild_id): ValidatedPath<GuildId>,
+    ValidatedJson(payload): ValidatedJson<GuildName>,
+) -> Result<Json<Message>, (StatusCode, Json<Message>)> {
+    FieldErrors::default()
+        .not_empty("guild_name", &payload.guild_name)
+        .max_chars("guild_name", &payload.guild_name, GUILD_NAME_MAX_CHARS)
+        .finish()?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let result = query(
+        &con,
+        "UPDATE channels SET guild_name = $1, updated_at = NOW() WHERE guild_id = $2 RETURNING channel_id",
+        &[&payload.guild_name, &guild_id],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let channel_ids: Vec<String> = result
+        .iter()
+        .map(|row| {
+            let channel_id: ChannelId = row.get("channel_id");
+            events::publish(Operation::Update, channel_id);
+            channel_id.to_string()
+        })
+        .collect();
+
+    let msg = Message {
+        message: format!("Renamed {} channels", channel_ids.len()),
+        data: Some(channel_ids),
+        ..Default::default()
+    };
+
+    Ok(Json(msg))
+}
+
+#[utoipa::path(
+    put,
+    path = "/guild/{guildid}/defaults",
+    tag = "guilds",
+    params(("guildid" = i64, Path, description = "Discord guild ID")),
+    request_body = GuildDefaults,
+    responses(
+        (status = 200, description = "Defaults stored", body = GuildDefaults),
+        (status = 400, description = "Malformed body or guild ID", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn set_defaults(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(guild_id): ValidatedPath<GuildId>,
+    ValidatedJson(payload): ValidatedJson<GuildDefaults>,
+) -> Result<Json<GuildDefaults>, (StatusCode, Json<Message>)> {
+    validate_snowflake("guild_id", guild_id)?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    execute(
+        &con,
+        "INSERT INTO guild_defaults VALUES ($1, $2) ON CONFLICT (guild_id) DO UPDATE SET suppress = EXCLUDED.suppress",
+        &[&guild_id, &payload.suppress],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok(Json(payload))
+}
+
+// Soft-deleted channels and their subscriptions are left out.
+#[utoipa::path(
+    get,
+    path = "/guild/{guildid}/export",
+    tag = "guilds",
+    params(("guildid" = i64, Path, description = "Discord guild ID")),
+    responses(
+        (status = 200, description = "Channels and subscriptions of the guild, empty if it has none", body = GuildExport),
+        (status = 400, description = "Malformed guild ID", body = Message),
+    )
+)]
+pub async fn export(
+    State(Replica(pool)): State<Replica>,
+    ValidatedPath(guild_id): ValidatedPath<GuildId>,
+) -> Result<Json<GuildExport>, (StatusCode, Json<Message>)> {
+    validate_snowflake("guild_id", guild_id)?;
+
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let channels = query(
+        &con,
+        "SELECT channel_id, channel_name, guild_name, suppress, added_at, added_by FROM channels WHERE guild_id = $1 AND deleted_at IS NULL ORDER BY added_at, channel_id",
+        &[&guild_id],
+    )
+    .await
+    .map_err(internal_error)?
+    .iter()
+    .map(|row| ExportedChannel {
+            channel_id: row.get("channel_id"),
+            channel_name: row.get("channel_name"),
+            guild_name: row.get("guild_name"),
+            suppress: row.get("suppress"),
+            added_at: row.get("added_at"),
+            added_by: row.get("added_by"),
+        })
+        .collect();
+
+    let anilist = query(
+        &con,
+        "SELECT a.anilist_id, a.anilist_name, a.site_url, a.channel_id, a.added_by FROM anilist a JOIN channels c ON c.channel_id = a.channel_id WHERE c.guild_id = $1 AND c.deleted_at IS NULL ORDER BY a.channel_id, a.anilist_id",
+        &[&guild_id],
+    )
+    .await
+    .map_err(internal_error)?
+    .iter()
+    .map(UserData::from)
+    .collect();
+
+    Ok(Json(GuildExport { channels, anilist }))
+}
+
+// Takes the output of export. Everything is created in one transaction, so
+// a single conflicting channel or subscription leaves the guild untouched.
+#[utoipa::path(
+    post,
+    path = "/guild/{guildid}/import",
+    tag = "guilds",
+    params(("guildid" = i64, Path, description = "Discord guild ID")),
+    request_body = GuildExport,
+    responses(
+        (status = 201, description = "Numbers of channels and subscriptions created, in data", body = Message),
+        (status = 409, description = "A channel or subscription already exists, nothing was created", body = Message),
+        (status = 422, description = "Invalid fields, one per entry in data", body = Message),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn import(
+    State(pool): State<Arc<Pool>>,
+    ValidatedPath(guild_id): ValidatedPath<GuildId>,
+    ValidatedJson(payload): ValidatedJson<GuildExport>,
+) -> Result<(StatusCode, Json<Message>), (StatusCode, Json<Message>)> {
+    // Subscriptions may only point at channels in the same import, otherwise
+    // they would end up on some other guild's channel.
+    let channel_ids: Vec<ChannelId> = payload
+        .channels
+        .iter()
+        .map(|channel| channel.channel_id)
+        .collect();
+    FieldErrors::default()
+        .max_items("channels", payload.channels.len())
+        .max_items("anilist", payload.anilist.len())
+        .finish()?;
+
+    let mut errors = FieldErrors::default()
+        .snowflake("guild_id", guild_id)
+        .minted("guild_id", guild_id);
+    for (index, channel) in payload.channels.iter().enumerate() {
+        let field = |name| format!("channels[{index}].{name}");
+        errors = errors
+            .snowflake(&field("channel_id"), channel.channel_id)
+            .minted(&field("channel_id"), channel.channel_id)
+            .not_empty(&field("channel_name"), &channel.channel_name)
+            .snowflake(&field("added_by"), channel.added_by)
+            .minted(&field("added_by"), channel.added_by)
+            .admin_only(&field("added_at"), channel.added_at.is_some());
+    }
+    for user in &payload.anilist {
+        errors = errors.one_of("anilist.channel_id", user.channel_id, &channel_ids);
+    }
+    errors
+        .site_urls(
+            "anilist",
+            payload.anilist.iter().map(|user| user.site_url.as_str()),
+        )
+        .finish()?;
+
+    let GuildExport {
+        channels,
+        anilist: users,
+    } = payload;
+    let subscriptions = users.len();
+    with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            for channel in &channels {
+                execute(
+                    tx,
+                    "INSERT INTO channels (channel_id, channel_name, guild_id, guild_name, added_at, added_by, suppress) VALUES ($1, $2, $3, $4, COALESCE($5, NOW()), $6, $7)",
+                    &[
+                        &channel.channel_id,
+                        &channel.channel_name,
+                        &guild_id,
+                        &guild_name_or_unknown(Some(channel.guild_name.clone())),
+                        &channel.added_at,
+                        &channel.added_by,
+                        &channel.suppress,
+                    ],
+                )
+                .await
+                .map_err(internal_error)?;
+            }
+
+            for user in &users {
+                anilist::insert_user(tx, user, user.channel_id).await?;
+            }
+
+            Ok(())
+        })
+    })
+    .await?;
+
+    for &channel_id in &channel_ids {
+        events::publish(Operation::Create, channel_id);
+    }
+
+    let msg = Message {
+        message: format!(
+            "Imported {} channels with {subscriptions} subscriptions",
+            channel_ids.len()
+        ),
+        data: Some(vec![
+            channel_ids.len().to_string(),
+            subscriptions.to_string(),
+        ]),
+        ..Default::default()
+    };
+
+    Ok((StatusCode::CREATED, Json(msg)))
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{channel, guild, tests, util::validation::scope_admin_fields, AppState};
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        middleware,
+        routing::{get, post, put},
+        Router,
+    };
+    use deadpool_postgres::Pool;
+    use http_body_util::BodyExt;
+    use rand::{thread_rng, Rng};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn init() -> (Router, Pool) {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+
+        let app = Router::new()
+            .route("/guild", get(guild::list))
+            .route("/guild/summaries", post(guild::summaries))
+            .route("/guild/:guildid/name", put(guild::rename))
+            .route("/guild/:guildid/defaults", put(guild::set_defaults))
+            .route("/guild/:guildid/export", get(guild::export))
+            .route("/guild/:guildid/import", post(guild::import))
+            .route("/channel", post(channel::add))
+            .with_state(AppState::from(Arc::new(pool.clone())));
+
+        (app, pool)
+    }
+
+    async fn insert_channel(pool: &Pool, guild_id: i64, guild_name: &str) {
+        let con = pool.get().await.unwrap();
+        con.execute(
+            "INSERT INTO channels VALUES ($1, 'channel', $2, $3, NOW(), $4, FALSE)",
+            &[
+                &tests::snowflake(),
+                &guild_id,
+                &guild_name,
+                &tests::snowflake(),
+            ],
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn list_test() {
+        let (app, pool) = init().await;
+        let small = thread_rng().gen_range(1..=i64::MAX);
+        let large = thread_rng().gen_range(1..=i64::MAX);
+        insert_channel(&pool, small, "small").await;
+        for _ in 0..3 {
+            insert_channel(&pool, large, "large").await;
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/guild")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!([
+                {"guild_id": large, "guild_name": "large", "channel_count": 3},
+                {"guild_id": small, "guild_name": "small", "channel_count": 1},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn summaries_test() {
+        let (app, pool) = init().await;
+        let first = thread_rng().gen_range(1..=i64::MAX);
+        let second = thread_rng().gen_range(1..=i64::MAX);
+        let unknown = thread_rng().gen_range(1..=i64::MAX);
+        insert_channel(&pool, first, "first").await;
+        insert_channel(&pool, second, "second").await;
+        insert_channel(&pool, second, "second").await;
+        insert_channel(&pool, thread_rng().gen_range(1..=i64::MAX), "other").await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/guild/summaries")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(json!([first, second, unknown]).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let mut expected = [
+            json!({"guild_id": first, "guild_name": "first", "channel_count": 1}),
+            json!({"guild_id": second, "guild_name": "second", "channel_count": 2}),
+        ];
+        expected.sort_by_key(|guild| guild["guild_id"].as_i64());
+        assert_eq!(body, json!(expected));
+    }
+
+    #[tokio::test]
+    async fn rename_test() {
+        let (app, pool) = init().await;
+        let guild_id = thread_rng().gen_range(1..=i64::MAX);
+        insert_channel(&pool, guild_id, "old").await;
+        insert_channel(&pool, guild_id, "old").await;
+        insert_channel(&pool, thread_rng().gen_range(1..=i64::MAX), "other").await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri(format!("/guild/{guild_id}/name"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"guild_name":"New Name"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "Renamed 2 channels");
+
+        let con = pool.get().await.unwrap();
+        let names: Vec<String> = con
+            .query(
+                "SELECT guild_name FROM channels WHERE guild_id = $1",
+                &[&guild_id],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get("guild_name"))
+            .collect();
+        assert_eq!(names, ["New Name", "New Name"]);
+    }
+
+    #[tokio::test]
+    async fn rename_too_long_test() {
+        let (app, _pool) = init().await;
+        let body = json!({ "guild_name": "x".repeat(101) });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/guild/1/name")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn set_defaults_test() {
+        let (app, pool) = init().await;
+        let guild_id = tests::snowflake();
+        let channel_id = tests::snowflake();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri(format!("/guild/{guild_id}/defaults"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"suppress":true}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let channel = json!({
+            "channel_id": channel_id,
+            "channel_name": "channel",
+            "guild_id": guild_id,
+            "guild_name": "guild",
+            "added_by": tests::snowflake(),
+        });
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(channel.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let con = pool.get().await.unwrap();
+        let suppress: bool = con
+            .query_one(
+                "SELECT suppress FROM channels WHERE channel_id = $1",
+                &[&channel_id],
+            )
+            .await
+            .unwrap()
+            .get("suppress");
+        assert!(suppress);
+    }
+
+    #[tokio::test]
+    async fn export_test() {
+        let (app, pool) = init().await;
+        let guild_id = tests::snowflake();
+        insert_channel(&pool, guild_id, "guild").await;
+        insert_channel(&pool, guild_id, "guild").await;
+        insert_channel(&pool, thread_rng().gen_range(1..=i64::MAX), "other").await;
+
+        let con = pool.get().await.unwrap();
+        let channel_id: i64 = con
+            .query_one(
+                "SELECT channel_id FROM channels WHERE guild_id = $1 LIMIT 1",
+                &[&guild_id],
+            )
+            .await
+            .unwrap()
+            .get("channel_id");
+        con.execute(
+            "INSERT INTO anilist VALUES (1, 'user', 'https://anilist.co/user/1', $1, NOW(), 1)",
+            &[&channel_id],
+        )
+        .await
+        .unwrap();
+        drop(con);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/guild/{guild_id}/export"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let export = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&export).unwrap();
+        assert_eq!(body["channels"].as_array().unwrap().len(), 2);
+        assert_eq!(body["anilist"].as_array().unwrap().len(), 1);
+        assert_eq!(body["anilist"][0]["channel_id"], channel_id);
+
+        // Wipe the guild and bring it back from the export. Keeping the
+        // original added_at needs the admin fields.
+        tests::reset(&pool).await;
+
+        let import = || {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/guild/{guild_id}/import"))
+                .header("Content-Type", "application/json")
+                .body(Body::from(export.clone()))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(import()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app
+            .layer(middleware::from_fn_with_state(true, scope_admin_fields))
+            .oneshot(import())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let con = pool.get().await.unwrap();
+        let count: i64 = con
+            .query_one(
+                "SELECT COUNT(*) FROM channels WHERE guild_id = $1",
+                &[&guild_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 2);
+        let count: i64 = con
+            .query_one("SELECT COUNT(*) FROM anilist", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn import_invalid_test() {
+        let (app, pool) = init().await;
+        let guild_id = tests::snowflake();
+        let channel_id = tests::snowflake();
+        let body = json!({
+            "channels": [{
+                "channel_id": channel_id,
+                "channel_name": "channel",
+                "guild_name": "guild",
+                "suppress": false,
+                "added_by": tests::snowflake(),
+            }],
+            "anilist": [{
+                "anilist_id": 1,
+                "anilist_name": "user",
+                "site_url": "http://anilist.co/user/1",
+                "channel_id": channel_id,
+                "added_by": tests::snowflake(),
+            }],
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/guild/{guild_id}/import"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["data"],
+            json!(["anilist[0].site_url: not an https://anilist.co URL"])
+        );
+        let con = pool.get().await.unwrap();
+        let count: i64 = con
+            .query_one("SELECT COUNT(*) FROM channels", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn import_empty_guild_name_test() {
+        let (app, pool) = init().await;
+        let guild_id = tests::snowflake();
+        let body = json!({
+            "channels": [{
+                "channel_id": tests::snowflake(),
+                "channel_name": "channel",
+                "guild_name": "",
+                "suppress": false,
+                "added_by": tests::snowflake(),
+            }],
+            "anilist": [],
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/guild/{guild_id}/import"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let con = pool.get().await.unwrap();
+        let guild_name: String = con
+            .query_one(
+                "SELECT guild_name FROM channels WHERE guild_id = $1",
+                &[&guild_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(guild_name, "Unknown");
+    }
+
+    #[tokio::test]
+    async fn import_epoch_test() {
+        let (app, pool) = init().await;
+        let guild_id = tests::snowflake();
+        let channel = |channel_id: i64, added_by: i64| {
+            json!({
+                "channel_id": channel_id,
+                "channel_name": "channel",
+                "guild_name": "guild",
+                "suppress": false,
+                "added_by": added_by,
+            })
+        };
+        let body = json!({
+            "channels": [
+                channel(1, tests::snowflake()),
+                channel(tests::snowflake(), tests::snowflake()),
+                channel(tests::snowflake(), i64::MAX),
+            ],
+            "anilist": [],
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/guild/{guild_id}/import"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["data"],
+            json!([
+                "channels[0].channel_id: timestamp isn't between 2015 and now",
+                "channels[2].added_by: timestamp isn't between 2015 and now",
+            ])
+        );
+        let con = pool.get().await.unwrap();
+        let count: i64 = con
+            .query_one("SELECT COUNT(*) FROM channels", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn export_empty_test() {
+        let (app, _pool) = init().await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/guild/1/export")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!({"channels": [], "anilist": []}));
+    }
+}
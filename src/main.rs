@@ -1,79 +1,1455 @@
+mod admin;
+mod anilist;
+mod audit;
 mod channel;
+mod config;
+mod guild;
+mod openapi;
+mod stats;
+mod types;
 mod util;
-// mod anilist;
 
+use admin::PurgeRetention;
 use axum::{
-    routing::{delete, get, post, put},
+    extract::FromRef,
+    middleware,
+    routing::{delete, get, patch, post, put},
     Router,
 };
-use deadpool_postgres::{Config, ManagerConfig, RecyclingMethod, Runtime};
+use channel::DuplicatePolicy;
+use deadpool_postgres::{Pool, Runtime, SslMode};
 use serde::Serialize;
-use std::{env, sync::Arc};
+use std::{env, net::SocketAddr, sync::Arc};
 use tokio_postgres::NoTls;
+use tower_http::{
+    compression::{
+        predicate::{DefaultPredicate, Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::Level;
+use util::{
+    accept::require_supported_accept,
+    auth::{require_api_key, require_api_key_for_reads, ApiKey},
+    body_limit::{body_limit_layer, bulk_body_bytes, payload_too_large_message},
+    cors::cors_layer,
+    db::Replica,
+    i18n::scope_language,
+    rate_limit::rate_limit_layer,
+    request_id::{make_span, scope_request_id},
+};
+use utoipa::{OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 
-#[derive(Serialize, Default)]
+#[derive(Serialize, Default, Debug, ToSchema)]
 struct Message {
     message: String,
+    /// Stable, machine-readable error slug such as `not_found`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sqlstate: Option<String>,
+    /// Violated constraint, e.g. `channels_pkey`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    constraint: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     data: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+// Handlers that write take `State<Arc<Pool>>` and get the primary, read-only
+// ones take `State<Replica>`.
+#[derive(Clone)]
+struct AppState {
+    primary: Arc<Pool>,
+    replica: Arc<Pool>,
+    duplicate_policy: DuplicatePolicy,
+    purge_retention: PurgeRetention,
+}
+
+// Without a replica, reads go to the primary too.
+impl From<Arc<Pool>> for AppState {
+    fn from(pool: Arc<Pool>) -> Self {
+        AppState {
+            primary: Arc::clone(&pool),
+            replica: pool,
+            duplicate_policy: DuplicatePolicy::default(),
+            purge_retention: PurgeRetention::default(),
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<Pool> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.primary)
+    }
+}
+
+impl FromRef<AppState> for Replica {
+    fn from_ref(state: &AppState) -> Self {
+        Replica(Arc::clone(&state.replica))
+    }
+}
+
+impl FromRef<AppState> for DuplicatePolicy {
+    fn from_ref(state: &AppState) -> Self {
+        state.duplicate_policy
+    }
+}
+
+impl FromRef<AppState> for PurgeRetention {
+    fn from_ref(state: &AppState) -> Self {
+        state.purge_retention
+    }
 }
 
 #[tokio::main]
 async fn main() {
+    let config = config::Config::from_env();
+    util::logging::init(config.log_format, config.log_sql);
+
     let host = env::var("HOST").expect("HOST ENV is missing");
     let db_name = env::var("DBNAME").expect("DBNAME ENV is missing");
     let username = env::var("USERNAME").expect("USERNAME ENV is missing");
     let password = env::var("PASSWORD").expect("PASSWORD ENV is Missing");
 
-    let mut config = Config::new();
-    config.host = Some(host);
-    config.dbname = Some(db_name);
-    config.user = Some(username);
-    config.password = Some(password);
-    config.manager = Some(ManagerConfig {
-        recycling_method: RecyclingMethod::Fast,
+    let mut db_config = deadpool_postgres::Config::new();
+    db_config.host = Some(host);
+    db_config.dbname = Some(db_name);
+    db_config.user = Some(username);
+    db_config.password = Some(password);
+    db_config.options = Some(util::db::connection_options(&config.db_schema));
+    db_config.manager = Some(util::db::manager_config(config.db_recycling));
+    db_config.pool = Some(util::db::pool_config(
+        config.pool_max_size,
+        config.pool_wait_timeout,
+    ));
+
+    let tls = util::tls::connector(config.db_sslmode, config.db_ca_cert.as_deref())
+        .unwrap_or_else(|err| panic!("Couldn't configure database TLS: {err}"));
+    if tls.is_some() {
+        db_config.ssl_mode = Some(SslMode::Require);
+    }
+    let pg_config = db_config
+        .get_pg_config()
+        .expect("Couldn't build Postgres config");
+    let create_pool = |db_config: &deadpool_postgres::Config| match tls.clone() {
+        Some(tls) => db_config.create_pool(Some(Runtime::Tokio1), tls),
+        None => db_config.create_pool(Some(Runtime::Tokio1), NoTls),
+    };
+    let pool = create_pool(&db_config).expect("Couldn't create connection pool");
+    let replica = env::var("REPLICA_HOST").ok().map(|host| {
+        let db_config = deadpool_postgres::Config {
+            host: Some(host),
+            ..db_config.clone()
+        };
+        let replica = create_pool(&db_config).expect("Couldn't create replica connection pool");
+        Arc::new(replica)
     });
 
-    let pool = config
-        .create_pool(Some(Runtime::Tokio1), NoTls)
-        .expect("Couldn't create connection pool");
+    let con = pool.get().await.expect("Couldn't connect to database");
+    util::db::create_schema(&con, &config.db_schema)
+        .await
+        .expect("Couldn't create database schema");
+    util::db::migrate(&con)
+        .await
+        .expect("Couldn't run database migrations");
+    drop(con);
 
     let arc_pool = Arc::new(pool);
+    let state = AppState {
+        replica: replica.unwrap_or_else(|| Arc::clone(&arc_pool)),
+        primary: Arc::clone(&arc_pool),
+        duplicate_policy: config.duplicate_policy,
+        purge_retention: config.purge_retention,
+    };
 
-    tracing_subscriber::fmt::init();
-    let app = Router::new()
-        .route("/channel", post(channel::add))
-        .route("/channel/:channelid", get(channel::get))
-        .route("/channel/:channelid", put(channel::update))
-        .route("/channel/:channelid", delete(channel::delete))
-        .with_state(arc_pool);
+    // Relays every replica's writes into this one's /channel/events.
+    match tls {
+        Some(tls) => util::notify::spawn(pg_config, tls),
+        None => util::notify::spawn(pg_config, NoTls),
+    }
+    util::notify::enable(Arc::clone(&arc_pool));
+
+    util::metrics::install();
+    let app = app(state, &config);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:80")
         .await
         .expect("Couldn't bind tcplistener");
 
-    axum::serve(listener, app)
-        .await
-        .expect("Couldn't serve service");
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("Couldn't serve service");
+}
+
+fn app(state: AppState, config: &config::Config) -> Router {
+    let api_key = ApiKey(Arc::from(config.api_key.as_str()));
+
+    // Bodies of up to MAX_BULK_ITEMS items, with a limit sized to match.
+    let bulk_bytes = bulk_body_bytes(config.max_body_bytes, config.max_bulk_items);
+    let bulk_routes = Router::new()
+        .route("/channel/batch-get", post(channel::get_many))
+        .route("/channel/sync", post(channel::sync))
+        .route("/channel/bulk-delete", post(channel::delete_bulk))
+        .route("/guild/summaries", post(guild::summaries))
+        .route("/guild/:guildid/import", post(guild::import))
+        .route("/channel/suppress", post(channel::set_suppress_many))
+        .route_layer(middleware::map_response_with_state(
+            bulk_bytes,
+            payload_too_large_message,
+        ))
+        .route_layer(body_limit_layer(bulk_bytes));
+
+    // The audit log and what's built on it, readable only with the API key
+    // unlike the rest of the GETs.
+    let audit_routes = Router::new()
+        .route("/audit", get(audit::list))
+        .route("/channel/:channelid/detail", get(channel::get_detail))
+        .route_layer(middleware::from_fn_with_state(
+            api_key.clone(),
+            require_api_key_for_reads,
+        ));
+
+    let mut routes = Router::new()
+        .route("/channel", post(channel::add))
+        .route("/channel", get(channel::list))
+        .route("/channel/search", get(channel::search))
+        .route("/channel/autocomplete", get(channel::autocomplete))
+        .route("/channel/recent", get(channel::recent))
+        .route("/channel/top", get(channel::most_subscribed))
+        .route("/channel/events", get(channel::events))
+        .route("/channel/export", get(channel::export_stream))
+        .route("/channel/:channelid", get(channel::get))
+        .route("/channel/:channelid", put(channel::put_replace))
+        .route("/channel/:channelid", patch(channel::update))
+        .route("/channel/:channelid", delete(channel::delete))
+        .route("/channel/full", post(channel::register_with_subscriptions))
+        .route("/guild", get(guild::list))
+        .route("/stats", get(stats::global))
+        .route("/guild/:guildid/name", put(guild::rename))
+        .route("/guild/:guildid/defaults", put(guild::set_defaults))
+        .route("/guild/:guildid/export", get(guild::export))
+        .route("/guild/:guildid/channel", delete(channel::delete_by_guild))
+        .route("/guild/:guildid/suppress", put(channel::set_guild_suppress))
+        .route(
+            "/channel/:channelid/suppress/toggle",
+            post(channel::toggle_suppress),
+        )
+        .route("/channel/:channelid/owner", put(channel::transfer_owner))
+        .route("/channel/:channelid/full", get(channel::get_full))
+        .route("/channel/:channelid/touch", post(channel::touch))
+        .route("/channel/:channelid/restore", post(channel::restore))
+        .route(
+            "/channel/:channelid/move/:to",
+            post(channel::move_subscriptions),
+        )
+        .route("/channel/:channelid/anilist", get(anilist::list_by_channel))
+        .route(
+            "/channel/:channelid/anilist/count",
+            get(anilist::count_by_channel),
+        )
+        .route("/anilist", post(anilist::add_user))
+        .route("/anilist", delete(anilist::remove_user))
+        .route("/admin/purge", post(admin::purge))
+        .merge(bulk_routes)
+        .merge(audit_routes);
+
+    if config.enable_admin_endpoints {
+        routes = routes.route("/admin/reset", post(admin::reset));
+    }
+    if config.wrap_responses {
+        routes = routes.layer(middleware::map_response(util::envelope::wrap_responses));
+    }
+
+    routes
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&state.primary),
+            audit::record,
+        ))
+        .route_layer(middleware::from_fn(util::metrics::track_metrics))
+        .route("/metrics", get(util::metrics::render))
+        .route("/health/detailed", get(util::health::detailed))
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", openapi::ApiDoc::openapi()))
+        .method_not_allowed_fallback(util::error_handling::method_not_allowed)
+        .fallback(util::error_handling::route_not_found)
+        .with_state(state)
+        .layer(middleware::map_response(util::error_handling::retry_after))
+        .layer(body_limit_layer(config.max_body_bytes))
+        .layer(middleware::map_response_with_state(
+            config.max_body_bytes,
+            payload_too_large_message,
+        ))
+        .layer(middleware::from_fn_with_state(api_key, require_api_key))
+        .layer(rate_limit_layer(config.rate_limit_rps))
+        .layer(middleware::from_fn(require_supported_accept))
+        .layer(
+            CompressionLayer::new()
+                .compress_when(DefaultPredicate::new().and(SizeAbove::new(1024))),
+        )
+        .layer(cors_layer(config.allowed_origins.as_deref()))
+        .layer(middleware::from_fn_with_state(
+            config.enable_admin_endpoints,
+            util::validation::scope_admin_fields,
+        ))
+        .layer(middleware::from_fn_with_state(
+            config.log_sql,
+            util::db::scope_log_sql,
+        ))
+        .layer(middleware::from_fn_with_state(
+            config.statement_cache,
+            util::db::scope_statement_cache,
+        ))
+        .layer(middleware::from_fn_with_state(
+            config.max_bulk_items,
+            util::validation::scope_max_bulk_items,
+        ))
+        .layer(middleware::from_fn_with_state(
+            config.snowflakes_as_strings,
+            types::scope_snowflakes_as_strings,
+        ))
+        .layer(middleware::from_fn(scope_language))
+        .layer(middleware::from_fn(scope_request_id))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
 }
 
 #[cfg(test)]
 mod tests {
-    use deadpool_postgres::{Pool, PoolConfig, Runtime};
-    use tokio_postgres::NoTls;
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{header, Method, Request, StatusCode},
+        Extension,
+    };
+    use chrono::Utc;
+    use deadpool_postgres::PoolConfig;
+    use http_body_util::BodyExt;
+    use rand::{thread_rng, Rng};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tower::{Service, ServiceExt};
+    use util::validation::DISCORD_EPOCH_MS;
 
-    pub fn pool() -> Pool {
+    const API_KEY: &str = "test-api-key";
+
+    // Requests served by axum::serve carry the peer address used for rate
+    // limiting; oneshot requests in tests need it added by hand.
+    fn with_peer(app: Router) -> Router {
+        app.layer(Extension(ConnectInfo(SocketAddr::from((
+            [127, 0, 0, 1],
+            8080,
+        )))))
+    }
+
+    fn config() -> config::Config {
+        config::Config {
+            api_key: API_KEY.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn db_config() -> deadpool_postgres::Config {
         // Docker Postgres Image
         // Database Name => anisocial
         // Default username, password => postgres
-        let pool_config = PoolConfig::new(1);
         let mut config = deadpool_postgres::Config::new();
         config.host = Some("localhost".to_string());
         config.dbname = Some("anisocial".to_string());
         config.user = Some("postgres".to_string());
         config.password = Some("postgres".to_string());
+
+        #[cfg(feature = "testcontainers")]
+        if env::var_os("USE_LOCAL_DB").is_none() {
+            let (host, port) = container::address();
+            config.host = Some(host);
+            config.port = Some(port);
+        }
+
+        config
+    }
+
+    #[cfg(feature = "testcontainers")]
+    mod container {
+        use std::{sync::OnceLock, thread};
+        use testcontainers_modules::{
+            postgres::Postgres,
+            testcontainers::{runners::SyncRunner, Container},
+        };
+
+        // One container shared by every test in the binary. Statics are never
+        // dropped, so it is left running until Docker cleans it up.
+        static POSTGRES: OnceLock<(Container<Postgres>, String, u16)> = OnceLock::new();
+
+        pub fn address() -> (String, u16) {
+            let (_, host, port) = POSTGRES.get_or_init(|| {
+                // The sync container API drives its own runtime, which can't be
+                // started from inside a #[tokio::test].
+                thread::spawn(|| {
+                    let container = Postgres::default()
+                        .with_db_name("anisocial")
+                        .start()
+                        .expect("Couldn't start Postgres container");
+                    let host = container.get_host().unwrap().to_string();
+                    let port = container.get_host_port_ipv4(5432).unwrap();
+                    (container, host, port)
+                })
+                .join()
+                .unwrap()
+            });
+
+            (host.clone(), *port)
+        }
+    }
+
+    pub fn pool() -> Pool {
+        let pool_config = PoolConfig::new(1);
+        let mut config = db_config();
         config.pool = Some(pool_config);
 
         config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap()
     }
+
+    // A snowflake minted some time in the last year, so it passes the epoch
+    // check on POST /channel.
+    pub fn snowflake() -> i64 {
+        let age = thread_rng().gen_range(0..365 * 24 * 60 * 60 * 1000);
+        let timestamp = Utc::now().timestamp_millis() - DISCORD_EPOCH_MS - age;
+        (timestamp << 22) | thread_rng().gen_range(0..1 << 22)
+    }
+
+    pub async fn reset(pool: &Pool) {
+        let con = pool.get().await.unwrap();
+        util::db::migrate(&con).await.unwrap();
+        con.batch_execute("DELETE FROM anilist; DELETE FROM channels; DELETE FROM guild_defaults; TRUNCATE audit_log;")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn cors_preflight_test() {
+        let config = config::Config {
+            allowed_origins: Some("http://admin.example.com, http://localhost:3000".to_string()),
+            ..config()
+        };
+        let app = with_peer(app(Arc::new(pool()).into(), &config));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/channel")
+                    .header(header::ORIGIN, "http://localhost:3000")
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                    .header(
+                        header::ACCESS_CONTROL_REQUEST_HEADERS,
+                        "content-type, authorization, if-match",
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://localhost:3000"
+        );
+        let allowed = response.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS]
+            .to_str()
+            .unwrap();
+        assert!(allowed.contains("authorization"), "{allowed}");
+        assert!(allowed.contains("if-match"), "{allowed}");
+    }
+
+    #[tokio::test]
+    async fn cors_unknown_origin_test() {
+        let config = config::Config {
+            allowed_origins: Some("http://admin.example.com".to_string()),
+            ..config()
+        };
+        let app = with_peer(app(Arc::new(pool()).into(), &config));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/channel")
+                    .header(header::ORIGIN, "http://evil.example.com")
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    // Real snowflakes, since POST /channel rejects IDs Discord couldn't have
+    // minted. This one is the example from Discord's docs.
+    const CHANNEL_ID: i64 = 175_928_847_299_117_063;
+
+    fn channel_body() -> Body {
+        Body::from(
+            json!({
+                "channel_id": CHANNEL_ID,
+                "channel_name": "channel",
+                "guild_id": CHANNEL_ID + 1,
+                "guild_name": "guild",
+                "added_by": CHANNEL_ID + 2
+            })
+            .to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn auth_missing_header_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .body(channel_body())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"message": "Missing API key", "code": "unauthorized"})
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_wrong_key_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .header(header::AUTHORIZATION, "Bearer not-the-key")
+                    .body(channel_body())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"message": "Invalid API key", "code": "unauthorized"})
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_correct_key_test() {
+        let pool = pool();
+        reset(&pool).await;
+        let app = with_peer(app(Arc::new(pool).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(channel_body())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            format!("/channel/{CHANNEL_ID}")
+        );
+    }
+
+    #[tokio::test]
+    async fn body_limit_test() {
+        let config = config::Config {
+            max_body_bytes: 1024,
+            ..config()
+        };
+        let app = with_peer(app(Arc::new(pool()).into(), &config));
+        let body = json!({
+            "channel_id": 1,
+            "channel_name": "x".repeat(2048),
+            "guild_id": 2,
+            "guild_name": "guild",
+            "added_by": 3
+        });
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "message": "Request body exceeds the limit of 1024 bytes",
+                "code": "payload_too_large"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn bulk_body_limit_test() {
+        let pool = pool();
+        reset(&pool).await;
+        let config = config();
+        let app = with_peer(app(Arc::new(pool).into(), &config));
+
+        // A full batch of channels with names as long as Discord allows.
+        let channels: Vec<Value> = (0..config.max_bulk_items)
+            .map(|_| {
+                json!({
+                    "channel_id": snowflake(),
+                    "channel_name": "c".repeat(100),
+                    "guild_id": snowflake(),
+                    "guild_name": "g".repeat(100),
+                    "added_by": snowflake(),
+                    "suppress": false
+                })
+            })
+            .collect();
+        let body = json!(channels).to_string();
+        assert!(body.len() > config.max_body_bytes);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel/sync")
+                    .header("Content-Type", "application/json")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn method_not_allowed_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel/1")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            response.headers()[header::ALLOW],
+            "GET,HEAD,PUT,PATCH,DELETE"
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "message": "Method POST is not allowed on /channel/1",
+                "code": "method_not_allowed"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn route_not_found_test() {
+        let pool = pool();
+        reset(&pool).await;
+        let mut app = with_peer(app(Arc::new(pool).into(), &config())).into_service();
+
+        let request = Request::builder()
+            .uri("/does-not-exist")
+            .body(Body::empty())
+            .unwrap();
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "message": "route not found", "code": "not_found" })
+        );
+
+        // Known routes keep their own 404 message.
+        let request = Request::builder()
+            .uri("/channel/1")
+            .body(Body::empty())
+            .unwrap();
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "message": "Could not find 1", "code": "not_found" })
+        );
+    }
+
+    #[tokio::test]
+    async fn accept_language_test() {
+        let pool = pool();
+        reset(&pool).await;
+        let app = with_peer(app(Arc::new(pool).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/channel/1")
+                    .header(header::ACCEPT_LANGUAGE, "es-ES,es;q=0.9,en;q=0.8")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "message": "No se encontró 1", "code": "not_found" })
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_skips_reads_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/channel/1/anilist")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn auth_audit_read_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        for uri in ["/audit", "/channel/1/detail"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/audit")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn unsupported_accept_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/channel/1/anilist")
+                    .header(header::ACCEPT, "application/xml")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "message": "Cannot produce a response matching Accept: application/xml",
+                "code": "not_acceptable",
+                "data": ["application/json", "text/event-stream", "text/csv", "application/x-ndjson"]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn wildcard_accept_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/channel/1/anilist")
+                    .header(header::ACCEPT, "text/html, application/*;q=0.8")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_test() {
+        let config = config::Config {
+            rate_limit_rps: 1,
+            ..config()
+        };
+        let mut app = app(Arc::new(pool()).into(), &config).into_service();
+        let client = ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000)));
+
+        let mut statuses = Vec::new();
+        for _ in 0..3 {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/channel")
+                .header("Content-Type", "application/json")
+                .body(channel_body())
+                .unwrap();
+            request.extensions_mut().insert(client);
+
+            let response = ServiceExt::<Request<Body>>::ready(&mut app)
+                .await
+                .unwrap()
+                .call(request)
+                .await
+                .unwrap();
+
+            statuses.push(response.status());
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                assert!(response.headers().contains_key(header::RETRY_AFTER));
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                assert!(body["message"]
+                    .as_str()
+                    .unwrap()
+                    .starts_with("Too many requests"));
+            }
+        }
+
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::UNAUTHORIZED,
+                StatusCode::UNAUTHORIZED,
+                StatusCode::TOO_MANY_REQUESTS
+            ]
+        );
+
+        let mut request = Request::builder()
+            .method("GET")
+            .uri("/channel/1/anilist")
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(client);
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn metrics_test() {
+        util::metrics::install();
+        let mut app = with_peer(app(Arc::new(pool()).into(), &config())).into_service();
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/channel/1/anilist")
+            .body(Body::empty())
+            .unwrap();
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/metrics")
+            .body(Body::empty())
+            .unwrap();
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let counter = body
+            .lines()
+            .find(|line| {
+                line.starts_with("http_requests_total{")
+                    && line.contains("path=\"/channel/:channelid/anilist\"")
+                    && line.contains("status=\"200\"")
+            })
+            .expect("request counter missing");
+        let count: u64 = counter.rsplit(' ').next().unwrap().parse().unwrap();
+        assert!(count >= 1);
+        assert!(body.contains("http_request_duration_seconds_bucket"));
+        assert!(body.contains("db_pool_available"));
+    }
+
+    #[tokio::test]
+    async fn compression_test() {
+        let pool = pool();
+        reset(&pool).await;
+        let con = pool.get().await.unwrap();
+        con.execute(
+            "INSERT INTO channels VALUES (1, 'channel', 2, 'guild', NOW(), 3, FALSE)",
+            &[],
+        )
+        .await
+        .unwrap();
+        for anilist_id in 0..50_i64 {
+            con.execute(
+                "INSERT INTO anilist VALUES ($1, 'some-anilist-user', 'https://anilist.co/user/some-anilist-user/', 1, NOW(), 3)",
+                &[&anilist_id],
+            )
+            .await
+            .unwrap();
+        }
+        drop(con);
+        let mut app = with_peer(app(Arc::new(pool).into(), &config())).into_service();
+
+        for (uri, compressed) in [("/channel/1/anilist", true), ("/channel/2/anilist", false)] {
+            let request = Request::builder()
+                .method("GET")
+                .uri(uri)
+                .header(header::ACCEPT_ENCODING, "gzip, br")
+                .body(Body::empty())
+                .unwrap();
+            let response = ServiceExt::<Request<Body>>::ready(&mut app)
+                .await
+                .unwrap()
+                .call(request)
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response.headers().contains_key(header::CONTENT_ENCODING),
+                compressed,
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn openapi_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let paths = body["paths"].as_object().unwrap();
+        for path in [
+            "/channel",
+            "/channel/{channelid}",
+            "/channel/{channelid}/anilist",
+        ] {
+            assert!(paths.contains_key(path), "{path} missing from spec");
+        }
+        assert!(paths["/channel/{channelid}"]["get"]["responses"]
+            .as_object()
+            .unwrap()
+            .contains_key("404"));
+    }
+
+    #[tokio::test]
+    async fn pool_timeout_test() {
+        let mut db_config = db_config();
+        db_config.pool = Some(util::db::pool_config(1, Duration::from_millis(100)));
+        let pool = db_config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+        let _held = pool.get().await.unwrap();
+        let app = with_peer(app(Arc::new(pool.clone()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/channel/1/anilist")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "service_unavailable");
+        assert_eq!(body["message"], "Server is busy, try again shortly");
+    }
+
+    #[tokio::test]
+    async fn request_id_test() {
+        let mut db_config = db_config();
+        db_config.password = Some("wrong-password".to_string());
+        let pool = db_config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+        let app = with_peer(app(Arc::new(pool).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/channel/1/anilist")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let request_id = response.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(!request_id.is_empty());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], request_id.as_str());
+    }
+
+    #[tokio::test]
+    async fn request_id_echo_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/does-not-exist")
+                    .header("x-request-id", "abc-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()["x-request-id"], "abc-123");
+    }
+
+    #[tokio::test]
+    async fn db_schema_test() {
+        let public = pool();
+        reset(&public).await;
+        let con = public.get().await.unwrap();
+        con.batch_execute("DROP SCHEMA IF EXISTS staging CASCADE")
+            .await
+            .unwrap();
+
+        let schema: util::db::Schema = "staging".parse().unwrap();
+        let mut db_config = db_config();
+        db_config.options = Some(util::db::connection_options(&schema));
+        db_config.pool = Some(PoolConfig::new(1));
+        let staging = db_config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+        {
+            let con = staging.get().await.unwrap();
+            util::db::create_schema(&con, &schema).await.unwrap();
+            util::db::migrate(&con).await.unwrap();
+        }
+        let app = with_peer(app(Arc::new(staging).into(), &config()));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/channel")
+                    .header("Content-Type", "application/json")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(channel_body())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(format!("/channel/{CHANNEL_ID}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let count = |table: &'static str| {
+            let con = &con;
+            async move {
+                let row = con
+                    .query_one(&format!("SELECT COUNT(*) FROM {table}"), &[])
+                    .await
+                    .unwrap();
+                row.get::<_, i64>(0)
+            }
+        };
+        assert_eq!(count("staging.channels").await, 1);
+        assert_eq!(count("public.channels").await, 0);
+    }
+
+    #[tokio::test]
+    async fn replica_test() {
+        let primary = pool();
+        reset(&primary).await;
+        let con = primary.get().await.unwrap();
+        con.batch_execute("DROP SCHEMA IF EXISTS replica CASCADE")
+            .await
+            .unwrap();
+        drop(con);
+
+        // A schema stands in for the replica, holding a channel the primary
+        // doesn't have.
+        let schema: util::db::Schema = "replica".parse().unwrap();
+        let mut db_config = db_config();
+        db_config.options = Some(util::db::connection_options(&schema));
+        db_config.pool = Some(PoolConfig::new(1));
+        let replica = db_config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+        let channel_id = thread_rng().gen_range(1..=i64::MAX);
+        {
+            let con = replica.get().await.unwrap();
+            util::db::create_schema(&con, &schema).await.unwrap();
+            util::db::migrate(&con).await.unwrap();
+            con.execute(
+                "INSERT INTO channels VALUES ($1, 'channel', 2, 'guild', NOW(), 3, FALSE)",
+                &[&channel_id],
+            )
+            .await
+            .unwrap();
+        }
+        let state = AppState {
+            replica: Arc::new(replica),
+            ..AppState::from(Arc::new(primary))
+        };
+        let app = with_peer(app(state, &config()));
+
+        for method in [Method::GET, Method::HEAD] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(format!("/channel/{channel_id}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // Writes still go to the primary, which has never seen the channel.
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/channel/{channel_id}/suppress/toggle"))
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn wrap_responses_test() {
+        for wrap_responses in [false, true] {
+            let config = config::Config {
+                wrap_responses,
+                ..config()
+            };
+            let app = with_peer(app(Arc::new(pool()).into(), &config));
+
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri("/channel/1/anilist/count")
+                        .header("x-request-id", "abc-123")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            let count = json!({ "channel_id": 1, "count": 0 });
+            if wrap_responses {
+                assert_eq!(body["data"], count);
+                assert_eq!(body["meta"]["request_id"], "abc-123");
+                assert!(body["meta"]["timestamp"].is_string());
+            } else {
+                assert_eq!(body, count);
+            }
+
+            // Errors are never wrapped.
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri("/channel/abc")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], "validation_error");
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_reset_test() {
+        let pool = pool();
+        reset(&pool).await;
+        let con = pool.get().await.unwrap();
+        con.batch_execute(
+            "INSERT INTO channels VALUES (1, 'channel', 2, 'guild', NOW(), 3, FALSE);
+             INSERT INTO anilist VALUES (4, 'user', 'https://anilist.co/user/4', 1, NOW(), 3);",
+        )
+        .await
+        .unwrap();
+        drop(con);
+
+        let config = config::Config {
+            enable_admin_endpoints: true,
+            ..config()
+        };
+        let app = with_peer(app(Arc::new(pool.clone()).into(), &config));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/admin/reset")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let con = pool.get().await.unwrap();
+        for table in ["anilist", "channels"] {
+            let count: i64 = con
+                .query_one(&format!("SELECT COUNT(*) FROM {table}"), &[])
+                .await
+                .unwrap()
+                .get(0);
+            assert_eq!(count, 0, "{table}");
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_reset_disabled_test() {
+        let app = with_peer(app(Arc::new(pool()).into(), &config()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/admin/reset")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn admin_purge_test() {
+        let pool = pool();
+        reset(&pool).await;
+        let con = pool.get().await.unwrap();
+        con.batch_execute(
+            "INSERT INTO channels VALUES (1, 'channel', 2, 'guild', NOW(), 3, FALSE);
+             INSERT INTO channels VALUES (5, 'channel', 2, 'guild', NOW(), 3, FALSE);
+             INSERT INTO anilist VALUES (4, 'user', 'https://anilist.co/user/4', 1, NOW(), 3);",
+        )
+        .await
+        .unwrap();
+        drop(con);
+
+        let purge = |retention| {
+            let state = AppState {
+                purge_retention: PurgeRetention(retention),
+                ..AppState::from(Arc::new(pool.clone()))
+            };
+            with_peer(app(state, &config())).oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/admin/purge")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let app = with_peer(app(Arc::new(pool.clone()).into(), &config()));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/channel/1")
+                    .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Still within the default retention.
+        let response = purge(PurgeRetention::default().0).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"], json!(["0"]));
+
+        let response = purge(Duration::ZERO).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"], json!(["1"]));
+
+        let con = pool.get().await.unwrap();
+        let channels: Vec<i64> = con
+            .query("SELECT channel_id FROM channels", &[])
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        assert_eq!(channels, [5]);
+        let subscriptions: i64 = con
+            .query_one("SELECT COUNT(*) FROM anilist", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(subscriptions, 0);
+    }
 }
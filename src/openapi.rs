@@ -0,0 +1,77 @@
+use crate::{admin, anilist, audit, channel, guild, stats, util::health, Message};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        channel::add,
+        channel::register_with_subscriptions,
+        channel::sync,
+        channel::list,
+        channel::get,
+        channel::get_full,
+        channel::get_detail,
+        channel::exists,
+        channel::search,
+        channel::autocomplete,
+        channel::recent,
+        channel::most_subscribed,
+        channel::events,
+        channel::export_stream,
+        channel::get_many,
+        channel::put_replace,
+        channel::update,
+        channel::delete,
+        channel::delete_bulk,
+        channel::delete_by_guild,
+        channel::toggle_suppress,
+        channel::transfer_owner,
+        channel::set_guild_suppress,
+        channel::set_suppress_many,
+        channel::touch,
+        channel::restore,
+        channel::move_subscriptions,
+        guild::list,
+        guild::summaries,
+        guild::rename,
+        guild::set_defaults,
+        guild::export,
+        guild::import,
+        anilist::list_by_channel,
+        anilist::count_by_channel,
+        anilist::add_user,
+        anilist::remove_user,
+        stats::global,
+        audit::list,
+        health::detailed,
+        admin::reset,
+        admin::purge,
+    ),
+    components(schemas(channel::Create, channel::Replace, channel::Registration, channel::Data, channel::ChannelPatch, channel::Mutation, channel::ChannelPage, channel::ChannelName, channel::RecentChannel, channel::TopChannel, channel::ChannelDetail, channel::ChannelHistory, channel::GuildSuppress, channel::SuppressMany, channel::Owner, guild::GuildSummary, guild::GuildName, guild::GuildDefaults, guild::ExportedChannel, guild::GuildExport, anilist::UserData, anilist::SubscriptionCount, anilist::AddUser, anilist::RemoveUser, anilist::User, stats::GlobalStats, audit::AuditEntry, health::Health, Message)),
+    modifiers(&ApiKeyAuth),
+    tags(
+        (name = "channels", description = "Discord channels the bot posts to"),
+        (name = "guilds", description = "Discord guilds with at least one channel"),
+        (name = "anilist", description = "AniList users subscribed to a channel"),
+        (name = "stats", description = "Counts for the status page"),
+        (name = "audit", description = "Every successful write, newest first"),
+        (name = "health", description = "Readiness of the service and its database pool"),
+        (name = "admin", description = "Maintenance; reset is test-only and disabled unless ENABLE_ADMIN_ENDPOINTS=true"),
+    )
+)]
+pub struct ApiDoc;
+
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
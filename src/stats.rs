@@ -0,0 +1,118 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::{
+    util::{
+        db::{query_one, with_retry, Replica},
+        error_handling::{internal_error, pool_error},
+    },
+    Message,
+};
+
+// Soft-deleted channels, and the subscriptions on them, aren't counted.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GlobalStats {
+    total_channels: i64,
+    total_guilds: i64,
+    total_subscriptions: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Top-line counts across every guild", body = GlobalStats),
+    )
+)]
+pub async fn global(
+    State(Replica(pool)): State<Replica>,
+) -> Result<Json<GlobalStats>, (StatusCode, Json<Message>)> {
+    let pool = Arc::clone(&pool);
+    let con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let row = query_one(
+        &con,
+        "SELECT \
+           (SELECT COUNT(*) FROM channels WHERE deleted_at IS NULL) AS total_channels, \
+           (SELECT COUNT(DISTINCT guild_id) FROM channels WHERE deleted_at IS NULL) AS total_guilds, \
+           (SELECT COUNT(*) FROM anilist JOIN channels USING (channel_id) WHERE deleted_at IS NULL) AS total_subscriptions",
+        &[],
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok(Json(GlobalStats {
+        total_channels: row.get("total_channels"),
+        total_guilds: row.get("total_guilds"),
+        total_subscriptions: row.get("total_subscriptions"),
+    }))
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{stats, tests, AppState};
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn global_test() {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+
+        // Three live channels across two guilds, plus a deleted one in a
+        // third guild whose subscription doesn't count either.
+        let con = pool.get().await.unwrap();
+        con.batch_execute(
+            "INSERT INTO channels VALUES (1, 'channel', 10, 'guild', NOW(), 100, FALSE);
+             INSERT INTO channels VALUES (2, 'channel', 10, 'guild', NOW(), 100, FALSE);
+             INSERT INTO channels VALUES (3, 'channel', 20, 'guild', NOW(), 100, FALSE);
+             INSERT INTO channels VALUES (4, 'channel', 30, 'guild', NOW(), 100, FALSE);
+             UPDATE channels SET deleted_at = NOW() WHERE channel_id = 4;
+             INSERT INTO anilist VALUES (1, 'user', 'https://anilist.co/user/1/', 1, NOW(), 100);
+             INSERT INTO anilist VALUES (2, 'user', 'https://anilist.co/user/2/', 1, NOW(), 100);
+             INSERT INTO anilist VALUES (1, 'user', 'https://anilist.co/user/1/', 3, NOW(), 100);
+             INSERT INTO anilist VALUES (1, 'user', 'https://anilist.co/user/1/', 4, NOW(), 100);",
+        )
+        .await
+        .unwrap();
+        drop(con);
+
+        let app = Router::new()
+            .route("/stats", get(stats::global))
+            .with_state(AppState::from(Arc::new(pool)));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/stats")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "total_channels": 3, "total_guilds": 2, "total_subscriptions": 3 })
+        );
+    }
+}
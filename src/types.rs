@@ -0,0 +1,216 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::{
+    de::{self, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{error::Error, fmt, num::ParseIntError, str::FromStr};
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use utoipa::ToSchema;
+
+tokio::task_local! {
+    // Follows SNOWFLAKES_AS_STRINGS. JavaScript numbers lose precision past
+    // 2^53, which most snowflakes are, so clients there can ask for strings.
+    static AS_STRINGS: bool;
+}
+
+// Scoped per request rather than a global, so each app, including the ones
+// tests build, decides its own wire format.
+pub async fn scope_snowflakes_as_strings(
+    State(enabled): State<bool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    AS_STRINGS.scope(enabled, next.run(request)).await
+}
+
+// Integers outside a request.
+pub fn snowflakes_as_strings() -> bool {
+    AS_STRINGS.try_with(|enabled| *enabled).unwrap_or(false)
+}
+
+// For streamed bodies, which are serialized after the request's scope ends.
+pub fn with_snowflakes_as_strings<R>(enabled: bool, f: impl FnOnce() -> R) -> R {
+    AS_STRINGS.sync_scope(enabled, f)
+}
+
+// Discord snowflakes, one type per kind of ID so a guild_id can't end up in
+// a channel_id parameter. In BIGINT columns they are plain integers, exactly
+// like the i64 they wrap, and on the wire too unless AS_STRINGS is set.
+macro_rules! snowflake {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, ToSchema)]
+        pub struct $name(pub i64);
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize(self.0, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize(deserializer).map($name)
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(id: $name) -> i64 {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIntError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                value.parse().map($name)
+            }
+        }
+
+        impl ToSql for $name {
+            fn to_sql(
+                &self,
+                ty: &Type,
+                out: &mut bytes::BytesMut,
+            ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+                self.0.to_sql(ty, out)
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                <i64 as ToSql>::accepts(ty)
+            }
+
+            to_sql_checked!();
+        }
+
+        impl<'a> FromSql<'a> for $name {
+            fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+                i64::from_sql(ty, raw).map($name)
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                <i64 as FromSql>::accepts(ty)
+            }
+        }
+    };
+}
+
+fn serialize<S: Serializer>(id: i64, serializer: S) -> Result<S::Ok, S::Error> {
+    if snowflakes_as_strings() {
+        serializer.collect_str(&id)
+    } else {
+        serializer.serialize_i64(id)
+    }
+}
+
+// In string mode integers are still accepted, so existing clients keep
+// working while they switch over.
+fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    if snowflakes_as_strings() {
+        deserializer.deserialize_any(SnowflakeVisitor)
+    } else {
+        deserializer.deserialize_i64(SnowflakeVisitor)
+    }
+}
+
+struct SnowflakeVisitor;
+
+impl Visitor<'_> for SnowflakeVisitor {
+    type Value = i64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if snowflakes_as_strings() {
+            f.write_str("a snowflake as a string or integer")
+        } else {
+            // Same wording as a plain i64 field, which these used to be.
+            f.write_str("i64")
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<i64, E> {
+        Ok(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<i64, E> {
+        i64::try_from(value).map_err(|_| E::invalid_value(Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<i64, E> {
+        if !snowflakes_as_strings() {
+            return Err(E::invalid_type(Unexpected::Str(value), &self));
+        }
+
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(Unexpected::Str(value), &self))
+    }
+}
+
+snowflake!(
+    /// Discord channel ID
+    ChannelId
+);
+snowflake!(
+    /// Discord guild ID
+    GuildId
+);
+snowflake!(
+    /// Discord user ID
+    UserId
+);
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn wire_format_test() {
+        let id = ChannelId(1_234_567_890_123_456_789);
+        assert_eq!(
+            serde_json::to_value(id).unwrap(),
+            json!(1_234_567_890_123_456_789i64)
+        );
+        assert_eq!(
+            serde_json::from_value::<ChannelId>(json!(42)).unwrap(),
+            ChannelId(42)
+        );
+        assert!(serde_json::from_value::<GuildId>(json!("42")).is_err());
+        assert_eq!("42".parse(), Ok(UserId(42)));
+        assert_eq!(UserId(42).to_string(), "42");
+    }
+
+    #[test]
+    fn as_strings_test() {
+        let id = ChannelId(1_234_567_890_123_456_789);
+        let (serialized, from_string, from_integer, invalid) =
+            with_snowflakes_as_strings(true, || {
+                (
+                    serde_json::to_value(id),
+                    serde_json::from_value::<ChannelId>(json!("1234567890123456789")),
+                    serde_json::from_value::<GuildId>(json!(42)),
+                    serde_json::from_value::<UserId>(json!("abc")),
+                )
+            });
+
+        assert_eq!(serialized.unwrap(), json!("1234567890123456789"));
+        assert_eq!(from_string.unwrap(), id);
+        assert_eq!(from_integer.unwrap(), GuildId(42));
+        assert!(invalid.is_err());
+    }
+}
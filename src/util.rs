@@ -1 +1,20 @@
+pub mod accept;
+pub mod auth;
+pub mod body_limit;
+pub mod cors;
+pub mod db;
+pub mod envelope;
 pub mod error_handling;
+pub mod etag;
+pub mod events;
+pub mod health;
+pub mod i18n;
+pub mod json;
+pub mod logging;
+pub mod metrics;
+pub mod notify;
+pub mod path;
+pub mod rate_limit;
+pub mod request_id;
+pub mod tls;
+pub mod validation;
@@ -0,0 +1,83 @@
+use crate::Message;
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+
+// text/event-stream is what EventSource sends for GET /channel/events,
+// text/csv is only produced by GET /channel and application/x-ndjson by
+// GET /channel/export.
+pub const SUPPORTED_TYPES: &[&str] = &[
+    "application/json",
+    "text/event-stream",
+    "text/csv",
+    "application/x-ndjson",
+];
+
+pub async fn require_supported_accept(
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<Message>)> {
+    let accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok());
+
+    match accept {
+        Some(accept) if !accepts_any(accept, SUPPORTED_TYPES) => {
+            let msg = Message {
+                message: format!("Cannot produce a response matching Accept: {accept}"),
+                code: Some("not_acceptable".to_string()),
+                data: Some(SUPPORTED_TYPES.iter().map(|t| t.to_string()).collect()),
+                ..Default::default()
+            };
+
+            Err((StatusCode::NOT_ACCEPTABLE, Json(msg)))
+        }
+        _ => Ok(next.run(request).await),
+    }
+}
+
+/// Whether any media range in an `Accept` header value covers one of `types`.
+/// Ranges with `q=0` are treated as explicitly refused.
+pub fn accepts_any(accept: &str, types: &[&str]) -> bool {
+    accept.split(',').any(|range| {
+        let mut parts = range.split(';').map(str::trim);
+        let media_range = parts.next().unwrap_or_default();
+        let refused = parts.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+
+        !refused && types.iter().any(|t| matches(media_range, t))
+    })
+}
+
+/// Whether `media_type` itself is listed, so wildcards keep the JSON default.
+pub fn names(headers: &HeaderMap, media_type: &str) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| {
+            accept.split(',').any(|range| {
+                let media_range = range.split(';').next().unwrap_or_default().trim();
+                media_range.eq_ignore_ascii_case(media_type) && accepts_any(range, &[media_type])
+            })
+        })
+}
+
+fn matches(media_range: &str, media_type: &str) -> bool {
+    if media_range == "*/*" || media_range.eq_ignore_ascii_case(media_type) {
+        return true;
+    }
+
+    match (media_range.split_once('/'), media_type.split_once('/')) {
+        (Some((range_type, "*")), Some((type_, _))) => range_type.eq_ignore_ascii_case(type_),
+        _ => false,
+    }
+}
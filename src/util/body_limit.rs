@@ -0,0 +1,51 @@
+use crate::Message;
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+// Room for one item of a bulk body. A channel with 100-character names,
+// Discord's limit, and every field set is well under this.
+const BULK_ITEM_BYTES: usize = 1024;
+
+#[derive(Clone, Copy)]
+struct Rewritten;
+
+pub fn body_limit_layer(max_bytes: usize) -> DefaultBodyLimit {
+    DefaultBodyLimit::max(max_bytes)
+}
+
+// The bulk routes take up to MAX_BULK_ITEMS items, which doesn't fit in the
+// default limit. Doubled because an import carries that many channels and as
+// many subscriptions.
+pub fn bulk_body_bytes(max_body_bytes: usize, max_bulk_items: usize) -> usize {
+    max_body_bytes.max(2 * max_bulk_items * BULK_ITEM_BYTES)
+}
+
+// Extractors reject oversized bodies with a plain-text 413; rewrite it into
+// our usual Message shape.
+pub async fn payload_too_large_message(
+    State(max_bytes): State<usize>,
+    response: Response,
+) -> Response {
+    // Already rewritten by the bulk routes with their own limit.
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE
+        || response.extensions().get::<Rewritten>().is_some()
+    {
+        return response;
+    }
+
+    let msg = Message {
+        message: format!("Request body exceeds the limit of {max_bytes} bytes"),
+        code: Some("payload_too_large".to_string()),
+        ..Default::default()
+    };
+
+    let mut response = (StatusCode::PAYLOAD_TOO_LARGE, Json(msg)).into_response();
+    response.extensions_mut().insert(Rewritten);
+    response
+}
@@ -0,0 +1,47 @@
+use crate::{channel::X_TOTAL_COUNT, util::request_id::X_REQUEST_ID};
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+pub fn cors_layer(allowed_origins: Option<&str>) -> CorsLayer {
+    let Some(allowed_origins) = allowed_origins else {
+        return if cfg!(debug_assertions) {
+            CorsLayer::very_permissive()
+        } else {
+            CorsLayer::new()
+        };
+    };
+
+    let origins: Vec<HeaderValue> = allowed_origins
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            HeaderValue::from_str(origin).expect("ALLOWED_ORIGINS contains an invalid origin")
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        // Writes need the bearer token, and the conditional headers back
+        // ETag and If-Unmodified-Since checks.
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static(X_REQUEST_ID),
+            header::IF_NONE_MATCH,
+            header::IF_MATCH,
+            header::IF_UNMODIFIED_SINCE,
+        ])
+        .expose_headers([
+            HeaderName::from_static(X_TOTAL_COUNT),
+            HeaderName::from_static(X_REQUEST_ID),
+            header::ETAG,
+        ])
+}
@@ -0,0 +1,650 @@
+use crate::{
+    util::error_handling::{internal_error, pool_error},
+    Message,
+};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use deadpool_postgres::{
+    GenericClient, ManagerConfig, Pool, PoolConfig, PoolError, RecyclingMethod, Timeouts,
+    Transaction,
+};
+use std::{fmt, future::Future, pin::Pin, str::FromStr, sync::Arc, time::Duration};
+use tokio_postgres::{types::ToSql, Client, Row, RowStream, Statement};
+
+const RETRY_BACKOFF: [Duration; 3] = [
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+];
+
+// Applied in order on startup. Each file must be safe to re-run against a
+// database that already has it applied.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "0001_create_tables",
+        include_str!("../../migrations/0001_create_tables.sql"),
+    ),
+    (
+        "0002_soft_delete",
+        include_str!("../../migrations/0002_soft_delete.sql"),
+    ),
+    (
+        "0003_updated_at",
+        include_str!("../../migrations/0003_updated_at.sql"),
+    ),
+    (
+        "0004_last_seen_at",
+        include_str!("../../migrations/0004_last_seen_at.sql"),
+    ),
+    (
+        "0005_channel_name_prefix_index",
+        include_str!("../../migrations/0005_channel_name_prefix_index.sql"),
+    ),
+    (
+        "0006_guild_defaults",
+        include_str!("../../migrations/0006_guild_defaults.sql"),
+    ),
+    (
+        "0007_audit_log",
+        include_str!("../../migrations/0007_audit_log.sql"),
+    ),
+    (
+        "0008_channels_changed_at",
+        include_str!("../../migrations/0008_channels_changed_at.sql"),
+    ),
+    (
+        "0009_audit_log_sqlstate",
+        include_str!("../../migrations/0009_audit_log_sqlstate.sql"),
+    ),
+];
+
+// A Postgres schema name, restricted to ^[A-Za-z_][A-Za-z0-9_]{0,62}$ since it
+// ends up in SQL text and cannot be passed as a parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema(String);
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema("public".to_string())
+    }
+}
+
+impl FromStr for Schema {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut chars = value.chars();
+        let valid = value.len() <= 63
+            && chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if valid {
+            Ok(Schema(value.to_string()))
+        } else {
+            Err(format!("invalid schema name {value:?}"))
+        }
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// Every connection starts with `schema` as its search_path, so the handlers'
+// unqualified table names resolve inside it and their prepared statements
+// stay cacheable.
+pub fn connection_options(schema: &Schema) -> String {
+    format!("-c search_path={schema}")
+}
+
+pub async fn create_schema(client: &Client, schema: &Schema) -> Result<(), tokio_postgres::Error> {
+    client
+        .batch_execute(&format!("CREATE SCHEMA IF NOT EXISTS {schema}"))
+        .await
+}
+
+// The pool read-only handlers query. It is the primary itself unless
+// REPLICA_HOST points at a read replica.
+#[derive(Clone)]
+pub struct Replica(pub Arc<Pool>);
+
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+// Connection failures are worth retrying since Postgres may be mid-failover.
+// Errors reported by the server itself (DbError) are not.
+impl Transient for PoolError {
+    fn is_transient(&self) -> bool {
+        matches!(self, PoolError::Backend(err) if err.as_db_error().is_none())
+    }
+}
+
+// Retries `f` with exponential backoff while it fails with a transient error.
+pub async fn with_retry<T, E, F, Fut>(mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Transient,
+{
+    for delay in RETRY_BACKOFF {
+        match f().await {
+            Err(err) if err.is_transient() => {
+                tracing::warn!("Transient database error, retrying in {delay:?}");
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+
+    f().await
+}
+
+tokio::task_local! {
+    // Follows STATEMENT_CACHE, see `prepare`.
+    static STATEMENT_CACHE: bool;
+    // Follows LOG_SQL, see `log_sql`.
+    static LOG_SQL: bool;
+}
+
+// Scoped per request like validation::scope_admin_fields, so tests can build
+// apps with and without the cache side by side.
+pub async fn scope_statement_cache(
+    State(enabled): State<bool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    STATEMENT_CACHE.scope(enabled, next.run(request)).await
+}
+
+pub async fn scope_log_sql(State(enabled): State<bool>, request: Request, next: Next) -> Response {
+    LOG_SQL.scope(enabled, next.run(request)).await
+}
+
+// Spawned tasks don't inherit task-locals, so work a request hands off, like
+// a NOTIFY, is wrapped in this to keep the request's settings.
+pub fn in_current_scope<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let log_sql = LOG_SQL.try_with(|enabled| *enabled).unwrap_or(false);
+    STATEMENT_CACHE.scope(statement_cache(), LOG_SQL.scope(log_sql, future))
+}
+
+fn statement_cache() -> bool {
+    STATEMENT_CACHE.try_with(|enabled| *enabled).unwrap_or(true)
+}
+
+// Prepares `sql` once per pooled connection and reuses the statement after
+// that, keyed on the SQL text. Pooled connections are recycled rather than
+// closed, so the cache survives between requests.
+pub async fn prepare(
+    client: &impl GenericClient,
+    sql: &str,
+) -> Result<Statement, tokio_postgres::Error> {
+    if statement_cache() {
+        client.prepare_cached(sql).await
+    } else {
+        client.prepare(sql).await
+    }
+}
+
+// Parameters are logged as-is, nothing stored in these tables is secret.
+fn log_sql(sql: &str, params: &[&(dyn ToSql + Sync)]) {
+    if LOG_SQL.try_with(|enabled| *enabled).unwrap_or(false) {
+        tracing::debug!(sql, ?params, "executing statement");
+    }
+}
+
+// `prepare` followed by the matching client call, logging the statement and
+// its parameters first when LOG_SQL is on.
+pub async fn query(
+    client: &impl GenericClient,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<Vec<Row>, tokio_postgres::Error> {
+    log_sql(sql, params);
+    let statement = prepare(client, sql).await?;
+    client.query(&statement, params).await
+}
+
+pub async fn query_one(
+    client: &impl GenericClient,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<Row, tokio_postgres::Error> {
+    log_sql(sql, params);
+    let statement = prepare(client, sql).await?;
+    client.query_one(&statement, params).await
+}
+
+pub async fn query_opt(
+    client: &impl GenericClient,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<Option<Row>, tokio_postgres::Error> {
+    log_sql(sql, params);
+    let statement = prepare(client, sql).await?;
+    client.query_opt(&statement, params).await
+}
+
+// Rows are streamed rather than collected, for results too big to hold.
+pub async fn query_raw(
+    client: &impl GenericClient,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<RowStream, tokio_postgres::Error> {
+    log_sql(sql, params);
+    let statement = prepare(client, sql).await?;
+    client.query_raw(&statement, params.iter().copied()).await
+}
+
+pub async fn execute(
+    client: &impl GenericClient,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<u64, tokio_postgres::Error> {
+    log_sql(sql, params);
+    let statement = prepare(client, sql).await?;
+    client.execute(&statement, params).await
+}
+
+// How a pooled connection is checked before it is handed out again. Fast
+// only looks at whether the client noticed its socket close, so a connection
+// dropped by a network blip can still be handed out once. Verified runs an
+// empty query first, a round trip on every checkout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Recycling {
+    #[default]
+    Fast,
+    Verified,
+}
+
+impl FromStr for Recycling {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "fast" => Ok(Recycling::Fast),
+            "verified" => Ok(Recycling::Verified),
+            _ => Err(format!("expected fast or verified, got {value:?}")),
+        }
+    }
+}
+
+pub fn manager_config(recycling: Recycling) -> ManagerConfig {
+    let recycling_method = match recycling {
+        Recycling::Fast => RecyclingMethod::Fast,
+        Recycling::Verified => RecyclingMethod::Verified,
+    };
+
+    ManagerConfig { recycling_method }
+}
+
+pub fn pool_config(max_size: usize, wait_timeout: Duration) -> PoolConfig {
+    PoolConfig {
+        max_size,
+        timeouts: Timeouts {
+            wait: Some(wait_timeout),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+pub async fn migrate(client: &Client) -> Result<(), tokio_postgres::Error> {
+    for (name, sql) in MIGRATIONS {
+        tracing::info!("Applying migration {name}");
+        client.batch_execute(sql).await?;
+    }
+
+    Ok(())
+}
+
+pub type TransactionFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, (StatusCode, Json<Message>)>> + Send + 'a>>;
+
+// Runs `f` inside a transaction, committing when it returns `Ok` and rolling
+// back when it returns `Err`.
+pub async fn with_transaction<T, F>(pool: &Pool, f: F) -> Result<T, (StatusCode, Json<Message>)>
+where
+    F: for<'a> FnOnce(&'a Transaction<'a>) -> TransactionFuture<'a, T>,
+{
+    let mut con = with_retry(|| pool.get()).await.map_err(pool_error)?;
+
+    let transaction = con.transaction().await.map_err(internal_error)?;
+
+    match f(&transaction).await {
+        Ok(value) => {
+            transaction.commit().await.map_err(internal_error)?;
+            Ok(value)
+        }
+        // The error that caused the rollback is the one worth reporting, a
+        // failed rollback only means the connection is gone.
+        Err(err) => {
+            if let Err(rollback_err) = transaction.rollback().await {
+                tracing::error!("Couldn't roll back transaction: {rollback_err}");
+            }
+            Err(err)
+        }
+    }
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests;
+    use deadpool_postgres::Runtime;
+    use rand::random;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+    use tokio_postgres::NoTls;
+    use tracing::Level;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    #[tokio::test]
+    async fn retry_test() {
+        let mut unreachable = tests::db_config();
+        unreachable.port = Some(1);
+        let unreachable = unreachable
+            .create_pool(Some(Runtime::Tokio1), NoTls)
+            .unwrap();
+        let pool = tests::pool();
+
+        let mut attempts = 0;
+        let result = with_retry(|| {
+            attempts += 1;
+            if attempts == 1 {
+                unreachable.get()
+            } else {
+                pool.get()
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up_test() {
+        let mut unreachable = tests::db_config();
+        unreachable.port = Some(1);
+        let unreachable = unreachable
+            .create_pool(Some(Runtime::Tokio1), NoTls)
+            .unwrap();
+
+        let mut attempts = 0;
+        let result = with_retry(|| {
+            attempts += 1;
+            unreachable.get()
+        })
+        .await;
+
+        assert!(matches!(result, Err(PoolError::Backend(_))));
+        assert_eq!(attempts, 4);
+    }
+
+    #[tokio::test]
+    async fn retry_skips_timeout_test() {
+        let mut config = tests::db_config();
+        config.pool = Some(pool_config(1, Duration::from_millis(10)));
+        let pool = config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+        let _held = pool.get().await.unwrap();
+
+        let mut attempts = 0;
+        let result = with_retry(|| {
+            attempts += 1;
+            pool.get()
+        })
+        .await;
+
+        assert!(matches!(result, Err(PoolError::Timeout(_))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn verified_recycling_test() {
+        let mut config = tests::db_config();
+        config.manager = Some(manager_config(Recycling::Verified));
+        config.pool = Some(pool_config(1, Duration::from_secs(5)));
+        let pool = config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+
+        let con = pool.get().await.unwrap();
+        let pid: i32 = con
+            .query_one("SELECT pg_backend_pid()", &[])
+            .await
+            .unwrap()
+            .get(0);
+        drop(con);
+
+        // Recycled as is while it's healthy.
+        let con = pool.get().await.unwrap();
+        let row = con.query_one("SELECT pg_backend_pid()", &[]).await.unwrap();
+        assert_eq!(row.get::<_, i32>(0), pid);
+        drop(con);
+
+        // Killed behind the pool's back, the check replaces it.
+        let other = tests::pool().get().await.unwrap();
+        other
+            .execute("SELECT pg_terminate_backend($1)", &[&pid])
+            .await
+            .unwrap();
+
+        let con = pool.get().await.unwrap();
+        let row = con.query_one("SELECT pg_backend_pid()", &[]).await.unwrap();
+        assert_ne!(row.get::<_, i32>(0), pid);
+    }
+
+    #[test]
+    fn recycling_test() {
+        assert_eq!("fast".parse(), Ok(Recycling::Fast));
+        assert_eq!("verified".parse(), Ok(Recycling::Verified));
+        assert!("clean".parse::<Recycling>().is_err());
+    }
+
+    #[tokio::test]
+    async fn pool_max_size_test() {
+        let mut config = tests::db_config();
+        config.pool = Some(pool_config(2, Duration::from_millis(100)));
+        let pool = config.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+        assert_eq!(pool.status().max_size, 2);
+
+        let _first = pool.get().await.unwrap();
+        let _second = pool.get().await.unwrap();
+        let third = pool.get().await;
+        assert!(matches!(third, Err(PoolError::Timeout(_))));
+        assert_eq!(pool.status().size, 2);
+    }
+
+    #[tokio::test]
+    async fn commit_test() {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+        let channel_id = random::<i64>();
+
+        let result = with_transaction(&pool, |tx| {
+            Box::pin(async move {
+                tx.execute(
+                    "INSERT INTO channels VALUES ($1, 'channel', 1, 'guild', NOW(), 1, FALSE)",
+                    &[&channel_id],
+                )
+                .await
+                .map_err(internal_error)
+            })
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 1);
+        let con = pool.get().await.unwrap();
+        let rows = con
+            .query(
+                "SELECT 1 FROM channels WHERE channel_id = $1",
+                &[&channel_id],
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rollback_test() {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+        let channel_id = random::<i64>();
+
+        let result: Result<(), _> = with_transaction(&pool, |tx| {
+            Box::pin(async move {
+                tx.execute(
+                    "INSERT INTO channels VALUES ($1, 'channel', 1, 'guild', NOW(), 1, FALSE)",
+                    &[&channel_id],
+                )
+                .await
+                .map_err(internal_error)?;
+
+                let msg = Message {
+                    message: "abort".to_string(),
+                    ..Default::default()
+                };
+                Err((StatusCode::BAD_REQUEST, Json(msg)))
+            })
+        })
+        .await;
+
+        let (status, _) = result.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let con = pool.get().await.unwrap();
+        let rows = con
+            .query(
+                "SELECT 1 FROM channels WHERE channel_id = $1",
+                &[&channel_id],
+            )
+            .await
+            .unwrap();
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn schema_test() {
+        assert_eq!(
+            "staging_2".parse::<Schema>().unwrap().to_string(),
+            "staging_2"
+        );
+        assert!("2fast".parse::<Schema>().is_err());
+        assert!("public; DROP TABLE channels".parse::<Schema>().is_err());
+        assert!("".parse::<Schema>().is_err());
+        assert!("x".repeat(64).parse::<Schema>().is_err());
+    }
+
+    #[tokio::test]
+    async fn prepare_after_recycle_test() {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+        let sql = "SELECT channel_id FROM channels WHERE channel_id = $1";
+
+        for _ in 0..3 {
+            let con = pool.get().await.unwrap();
+            let statement = prepare(&con, sql).await.unwrap();
+            let rows = con.query(&statement, &[&1i64]).await.unwrap();
+            assert!(rows.is_empty());
+            assert_eq!(con.statement_cache.size(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn prepare_uncached_test() {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+        let con = pool.get().await.unwrap();
+        con.statement_cache.clear();
+
+        STATEMENT_CACHE
+            .scope(false, async {
+                prepare(&con, "SELECT 1").await.unwrap();
+            })
+            .await;
+        assert_eq!(con.statement_cache.size(), 0);
+
+        // Handed-off work keeps the setting it was spawned under.
+        let spawned = STATEMENT_CACHE.sync_scope(false, || {
+            tokio::spawn(in_current_scope(async { statement_cache() }))
+        });
+        assert!(!spawned.await.unwrap());
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn log_sql_test() {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+        let con = pool.get().await.unwrap();
+        let sql = "SELECT channel_id FROM channels WHERE channel_id = $1";
+
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(buffer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        query_opt(&con, sql, &[&1i64]).await.unwrap();
+        assert!(buffer.0.lock().unwrap().is_empty());
+
+        let (result, deleted) = LOG_SQL
+            .scope(true, async {
+                let result = query_opt(&con, sql, &[&175_928_847_299_117_063i64]).await;
+                drop(con);
+                // Statements inside a transaction go through the same helpers.
+                let deleted = with_transaction(&pool, |tx| {
+                    Box::pin(async move {
+                        execute(tx, "DELETE FROM anilist WHERE channel_id = $1", &[&42i64])
+                            .await
+                            .map_err(internal_error)
+                    })
+                })
+                .await;
+                (result, deleted)
+            })
+            .await;
+
+        assert!(result.unwrap().is_none());
+        assert_eq!(deleted.unwrap(), 0);
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains(sql));
+        assert!(output.contains("175928847299117063"));
+        assert!(output.contains("DELETE FROM anilist WHERE channel_id = $1"));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::util::{error_handling::server_error, request_id};
+use axum::{
+    body::{to_bytes, Body},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+// With WRAP_RESPONSES=true, successful JSON bodies become
+// {"data": <body>, "meta": {"request_id": ..., "timestamp": ...}}.
+// Errors keep the plain Message shape.
+#[derive(Serialize)]
+struct Envelope {
+    data: Value,
+    meta: Meta,
+}
+
+#[derive(Serialize)]
+struct Meta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    timestamp: DateTime<Utc>,
+}
+
+// CSV, NDJSON, SSE and empty responses pass through untouched, only JSON has
+// somewhere to put the envelope.
+pub async fn wrap_responses(response: Response) -> Response {
+    if !response.status().is_success() || !is_json(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(err) => return server_error(&err).into_response(),
+    };
+    let Ok(data) = serde_json::from_slice(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let envelope = Envelope {
+        data,
+        meta: Meta {
+            request_id: request_id::current().filter(|id| !id.is_empty()),
+            timestamp: Utc::now(),
+        },
+    };
+    let body = match serde_json::to_vec(&envelope) {
+        Ok(body) => body,
+        Err(err) => return server_error(&err).into_response(),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
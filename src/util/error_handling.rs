@@ -1,22 +1,181 @@
-use crate::Message;
-use axum::{http::StatusCode, Json};
-use std::error::Error;
-use tokio_postgres::error::DbError;
-
-pub fn internal_error(err: Box<dyn Error>) -> (StatusCode, Json<Message>) {
-    if let Ok(db_error) = err.downcast::<DbError>() {
-        let msg = Message {
-            message: db_error.message().to_string(),
-            ..Default::default()
-        };
+use crate::{
+    audit,
+    util::{i18n, request_id},
+    Message,
+};
+use axum::{
+    http::{header, HeaderValue, Method, StatusCode, Uri},
+    response::Response,
+    Json,
+};
+use deadpool_postgres::PoolError;
+use std::fmt::Display;
+use tokio_postgres::error::{DbError, SqlState};
+
+const RETRY_AFTER_SECS: u64 = 1;
+
+// Errors reported by Postgres itself become a 409 carrying its message and
+// sqlstate, anything else (a dropped connection, a protocol error) is a 500.
+pub fn internal_error(err: tokio_postgres::Error) -> (StatusCode, Json<Message>) {
+    match err.as_db_error() {
+        Some(db_error) => {
+            audit::note_sqlstate(db_error.code().code());
+            let msg = Message {
+                message: db_error.message().to_string(),
+                code: Some(db_error_code(db_error).to_string()),
+                sqlstate: Some(db_error.code().code().to_string()),
+                constraint: db_error.constraint().map(str::to_string),
+                request_id: request_id::current(),
+                ..Default::default()
+            };
+
+            (StatusCode::CONFLICT, Json(msg))
+        }
+        None => server_error(&err),
+    }
+}
+
+// A pool timeout means every connection is busy, so it is reported as a 503
+// the client can retry. Failing to connect at all, even when Postgres sent
+// the error (e.g. bad credentials), is our problem rather than the client's.
+pub fn pool_error(err: PoolError) -> (StatusCode, Json<Message>) {
+    match err {
+        PoolError::Timeout(_) => {
+            let msg = Message {
+                message: "Server is busy, try again shortly".to_string(),
+                code: Some("service_unavailable".to_string()),
+                request_id: request_id::current(),
+                ..Default::default()
+            };
+
+            (StatusCode::SERVICE_UNAVAILABLE, Json(msg))
+        }
+        err => server_error(&err),
+    }
+}
 
-        (StatusCode::CONFLICT, Json(msg))
-    } else {
-        let msg = Message {
-            message: "INTERNAL SERVER ERROR".to_string(),
-            ..Default::default()
+// Error handlers only return a status and a Message, so the header is added
+// to every 503 on the way out instead.
+pub async fn retry_after(mut response: Response) -> Response {
+    if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+        response
+            .headers_mut()
+            .entry(header::RETRY_AFTER)
+            .or_insert(HeaderValue::from(RETRY_AFTER_SECS));
+    }
+
+    response
+}
+
+pub fn server_error(err: &dyn Display) -> (StatusCode, Json<Message>) {
+    tracing::error!("{err}");
+
+    let msg = Message {
+        message: "INTERNAL SERVER ERROR".to_string(),
+        code: Some("internal_error".to_string()),
+        request_id: request_id::current(),
+        ..Default::default()
+    };
+
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(msg))
+}
+
+// Maps a Postgres error to one of our error codes, by sqlstate class
+// (the first two characters) unless a more specific code applies.
+fn db_error_code(db_error: &DbError) -> &'static str {
+    let sqlstate = db_error.code();
+    if *sqlstate == SqlState::UNIQUE_VIOLATION {
+        return match db_error.table() {
+            Some("channels") => "duplicate_channel",
+            Some("anilist") => "duplicate_subscription",
+            _ => "duplicate",
         };
+    }
+    if *sqlstate == SqlState::FOREIGN_KEY_VIOLATION {
+        return "foreign_key_violation";
+    }
+
+    match &sqlstate.code()[..2] {
+        "22" | "23" => "validation_error",
+        "40" => "transaction_conflict",
+        "53" | "57" => "service_unavailable",
+        _ => "database_error",
+    }
+}
+
+// The router adds the Allow header listing the methods registered for the path.
+pub async fn method_not_allowed(method: Method, uri: Uri) -> (StatusCode, Json<Message>) {
+    let msg = Message {
+        message: format!("Method {method} is not allowed on {}", uri.path()),
+        code: Some("method_not_allowed".to_string()),
+        ..Default::default()
+    };
+
+    (StatusCode::METHOD_NOT_ALLOWED, Json(msg))
+}
+
+pub async fn route_not_found() -> (StatusCode, Json<Message>) {
+    let msg = Message {
+        message: i18n::route_not_found(),
+        code: Some("not_found".to_string()),
+        ..Default::default()
+    };
+
+    (StatusCode::NOT_FOUND, Json(msg))
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests;
+    use tokio_postgres::NoTls;
+
+    #[tokio::test]
+    async fn internal_error_db_error_test() {
+        let con = tests::pool().get().await.unwrap();
+        let err = con
+            .execute("SELECT * FROM missing_table", &[])
+            .await
+            .unwrap_err();
+
+        let (status, Json(msg)) = internal_error(err);
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(msg.sqlstate.as_deref(), Some("42P01"));
+        assert_eq!(msg.code.as_deref(), Some("database_error"));
+        assert!(msg.message.contains("missing_table"), "{}", msg.message);
+        assert_eq!(msg.constraint, None);
+    }
+
+    #[tokio::test]
+    async fn internal_error_constraint_test() {
+        let pool = tests::pool();
+        tests::reset(&pool).await;
+        let con = pool.get().await.unwrap();
+        let insert = "INSERT INTO channels VALUES (1, 'channel', 1, 'guild', NOW(), 1, FALSE)";
+        con.execute(insert, &[]).await.unwrap();
+        let err = con.execute(insert, &[]).await.unwrap_err();
+
+        let (status, Json(msg)) = internal_error(err);
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(msg.sqlstate.as_deref(), Some("23505"));
+        assert_eq!(msg.constraint.as_deref(), Some("channels_pkey"));
+    }
+
+    #[tokio::test]
+    async fn internal_error_connection_error_test() {
+        // Nothing listens on port 1, so this fails before reaching Postgres.
+        let err = tokio_postgres::connect("host=127.0.0.1 port=1 user=postgres", NoTls)
+            .await
+            .err()
+            .unwrap();
 
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(msg))
+        let (status, Json(msg)) = internal_error(err);
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(msg.code.as_deref(), Some("internal_error"));
+        assert_eq!(msg.sqlstate, None);
     }
 }
@@ -0,0 +1,53 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+// SHA-256 of the JSON encoding, so the tag stays the same across builds and
+// replicas, which DefaultHasher doesn't promise.
+pub fn etag(value: &impl Serialize) -> HeaderValue {
+    let json = serde_json::to_vec(value).expect("ETag input serializes to JSON");
+    let digest = Sha256::digest(&json);
+
+    let mut tag = String::from("\"");
+    for byte in digest {
+        write!(tag, "{byte:02x}").expect("writing to a String can't fail");
+    }
+    tag.push('"');
+
+    HeaderValue::from_str(&tag).expect("hex digest is a valid header value")
+}
+
+// Weak comparison as required for If-None-Match (RFC 9110 13.1.2).
+pub fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Some(etag) = etag.to_str().ok() else {
+        return false;
+    };
+
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/"))
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fixed, so a tag handed out by one build or replica still matches on
+    // another.
+    #[test]
+    fn stable_etag_test() {
+        assert_eq!(
+            etag(&(1, "a")),
+            "\"2010945388e2de98f5651051478912aa4ff38bb13a2cdb1a2c257bb97fbf98ff\""
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{types::ChannelId, util::notify};
+use serde::Serialize;
+use std::{str::FromStr, sync::OnceLock};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+// Slow subscribers that fall further behind than this miss events rather
+// than holding up the write handlers.
+const CAPACITY: usize = 256;
+
+static SENDER: OnceLock<Sender<ChannelEvent>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Create,
+    Update,
+    Delete,
+}
+
+impl Operation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operation::Create => "create",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+impl FromStr for Operation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "create" => Ok(Operation::Create),
+            "update" => Ok(Operation::Update),
+            "delete" => Ok(Operation::Delete),
+            _ => Err(format!("unknown operation {value:?}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChannelEvent {
+    pub operation: Operation,
+    pub channel_id: ChannelId,
+}
+
+fn sender() -> &'static Sender<ChannelEvent> {
+    SENDER.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+// Call only after the change is committed. Having nobody subscribed is fine.
+// With the NOTIFY bridge running the event goes through Postgres, so every
+// replica, this one included, hears about it the same way.
+pub fn publish(operation: Operation, channel_id: ChannelId) {
+    let event = ChannelEvent {
+        operation,
+        channel_id,
+    };
+
+    if !notify::forward(event) {
+        deliver(event);
+    }
+}
+
+// Hands the event to this instance's subscribers only.
+pub fn deliver(event: ChannelEvent) {
+    let _ = sender().send(event);
+}
+
+pub fn subscribe() -> Receiver<ChannelEvent> {
+    sender().subscribe()
+}
@@ -0,0 +1,118 @@
+use axum::{extract::State, http::StatusCode, Json};
+use deadpool_postgres::Pool;
+use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use utoipa::ToSchema;
+
+// Requests queued for a connection before the service reports itself as
+// unavailable, given none are idle.
+const WAITING_THRESHOLD: usize = 8;
+
+// Latency of the last successful probe in microseconds, 0 before the first.
+static LAST_LATENCY_MICROS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Health {
+    /// "ok", or "unavailable" when the pool is exhausted or Postgres is unreachable
+    status: &'static str,
+    size: usize,
+    available: usize,
+    waiting: usize,
+    max_size: usize,
+    /// Round trip of the last successful SELECT 1
+    db_latency_ms: Option<f64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/detailed",
+    tag = "health",
+    responses(
+        (status = 200, description = "Pool statistics and database latency", body = Health),
+        (status = 503, description = "Pool exhausted or database unreachable", body = Health),
+    )
+)]
+pub async fn detailed(State(pool): State<Arc<Pool>>) -> (StatusCode, Json<Health>) {
+    let before = pool.status();
+    // Probing an exhausted pool would only queue behind everyone else.
+    let exhausted = before.available == 0 && before.waiting >= WAITING_THRESHOLD;
+    let healthy = !exhausted && probe(&pool).await;
+
+    let status = pool.status();
+    let last = LAST_LATENCY_MICROS.load(Ordering::Relaxed);
+    let health = Health {
+        status: if healthy { "ok" } else { "unavailable" },
+        size: status.size,
+        available: status.available,
+        waiting: status.waiting,
+        max_size: status.max_size,
+        db_latency_ms: (last > 0).then(|| last as f64 / 1000.0),
+    };
+
+    let code = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, Json(health))
+}
+
+async fn probe(pool: &Pool) -> bool {
+    let Ok(con) = pool.get().await else {
+        return false;
+    };
+
+    let start = Instant::now();
+    if con.simple_query("SELECT 1").await.is_err() {
+        return false;
+    }
+
+    let micros = u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX);
+    LAST_LATENCY_MICROS.store(micros.max(1), Ordering::Relaxed);
+    true
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn detailed_test() {
+        let app = Router::new()
+            .route("/health/detailed", get(detailed))
+            .with_state(Arc::new(tests::pool()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health/detailed")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["max_size"], 1);
+        assert!(body["available"].is_u64());
+        assert!(body["db_latency_ms"].as_f64().unwrap() > 0.0);
+    }
+}
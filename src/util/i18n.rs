@@ -0,0 +1,156 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::fmt::Display;
+
+// Languages the handler-written messages come in. Postgres messages, and
+// the per-field entries in `data`, stay in English.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Language {
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split('-').next().unwrap_or_default();
+        match primary.trim().to_ascii_lowercase().as_str() {
+            "en" => Some(Language::English),
+            "es" => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+}
+
+tokio::task_local! {
+    static LANGUAGE: Language;
+}
+
+// Same idea as request_id::scope_request_id, so the messages built deep in
+// handlers can follow Accept-Language without every handler extracting it.
+pub async fn scope_language(request: Request, next: Next) -> Response {
+    let language = preferred(request.headers());
+    LANGUAGE.scope(language, next.run(request)).await
+}
+
+pub fn current() -> Language {
+    LANGUAGE.try_with(|language| *language).unwrap_or_default()
+}
+
+// The supported language with the highest q, the first listed on a tie.
+// Anything unsupported or unparsable falls back to English.
+fn preferred(headers: &HeaderMap) -> Language {
+    let Some(accept_language) = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Language::default();
+    };
+
+    let mut best: Option<(Language, f32)> = None;
+    for range in accept_language.split(',') {
+        let mut parts = range.split(';');
+        let Some(language) = parts.next().and_then(Language::from_tag) else {
+            continue;
+        };
+        let q = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())
+            .unwrap_or(0.0);
+
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((language, q));
+        }
+    }
+
+    best.map(|(language, _)| language).unwrap_or_default()
+}
+
+pub fn not_found(id: impl Display) -> String {
+    match current() {
+        Language::English => format!("Could not find {id}"),
+        Language::Spanish => format!("No se encontró {id}"),
+    }
+}
+
+pub fn deleted_not_found(id: impl Display) -> String {
+    match current() {
+        Language::English => format!("Could not find deleted channel {id}"),
+        Language::Spanish => format!("No se encontró el canal eliminado {id}"),
+    }
+}
+
+pub fn route_not_found() -> String {
+    match current() {
+        Language::English => "route not found".to_string(),
+        Language::Spanish => "ruta no encontrada".to_string(),
+    }
+}
+
+pub fn invalid_fields(count: usize) -> String {
+    match current() {
+        Language::English => format!("{count} invalid fields"),
+        Language::Spanish => format!("{count} campos no válidos"),
+    }
+}
+
+pub fn not_positive_snowflake(field: &str, value: i64) -> String {
+    match current() {
+        Language::English => {
+            format!("{field} must be a positive Discord snowflake, got {value}")
+        }
+        Language::Spanish => {
+            format!("{field} debe ser un snowflake de Discord positivo, se recibió {value}")
+        }
+    }
+}
+
+pub fn not_minted_snowflake(field: &str, value: i64) -> String {
+    match current() {
+        Language::English => format!(
+            "{field} {value} is not a Discord snowflake, its timestamp isn't between 2015 and now"
+        ),
+        Language::Spanish => format!(
+            "{field} {value} no es un snowflake de Discord, su marca de tiempo no está entre 2015 y ahora"
+        ),
+    }
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn preferred_for(value: &'static str) -> Language {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static(value));
+        preferred(&headers)
+    }
+
+    #[test]
+    fn preferred_test() {
+        assert_eq!(preferred(&HeaderMap::new()), Language::English);
+        assert_eq!(preferred_for("es"), Language::Spanish);
+        assert_eq!(preferred_for("es-MX,es;q=0.9"), Language::Spanish);
+        assert_eq!(preferred_for("fr, es;q=0.5, en;q=0.8"), Language::English);
+        assert_eq!(preferred_for("de, *;q=0.5"), Language::English);
+        assert_eq!(preferred_for("es;q=0"), Language::English);
+    }
+
+    #[tokio::test]
+    async fn scoped_test() {
+        assert_eq!(not_found(1), "Could not find 1");
+        let message = LANGUAGE
+            .scope(Language::Spanish, async { not_found(1) })
+            .await;
+        assert_eq!(message, "No se encontró 1");
+    }
+}
@@ -0,0 +1,168 @@
+use crate::Message;
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+// Drop-in replacement for `axum::Json` that reports body errors as a 400
+// Message instead of axum's plain-text 422, and a non-JSON Content-Type as
+// a 415 Message.
+pub(crate) struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<Message>);
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json(request.headers()) {
+            let msg = Message {
+                message: "Expected Content-Type: application/json".to_string(),
+                code: Some("unsupported_media_type".to_string()),
+                ..Default::default()
+            };
+
+            return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, Json(msg)));
+        }
+
+        let body = Bytes::from_request(request, state)
+            .await
+            .map_err(|rejection| {
+                let msg = Message {
+                    message: rejection.body_text(),
+                    code: Some("validation_error".to_string()),
+                    ..Default::default()
+                };
+
+                (rejection.status(), Json(msg))
+            })?;
+
+        if body.is_empty() {
+            let msg = Message {
+                message: "Request body is empty".to_string(),
+                code: Some("validation_error".to_string()),
+                ..Default::default()
+            };
+
+            return Err((StatusCode::BAD_REQUEST, Json(msg)));
+        }
+
+        match Json::<T>::from_bytes(&body) {
+            Ok(Json(value)) => Ok(ValidatedJson(value)),
+            Err(rejection) => {
+                let status = match rejection {
+                    JsonRejection::JsonDataError(_) | JsonRejection::JsonSyntaxError(_) => {
+                        StatusCode::BAD_REQUEST
+                    }
+                    _ => rejection.status(),
+                };
+
+                let message = rejection.body_text();
+                let msg = Message {
+                    data: unknown_field(&message)
+                        .map(|field| vec![format!("{field}: unknown field")]),
+                    message,
+                    code: Some("validation_error".to_string()),
+                    ..Default::default()
+                };
+
+                Err((status, Json(msg)))
+            }
+        }
+    }
+}
+
+// Same rule as axum's Json: application/json or any application/*+json,
+// parameters such as charset allowed.
+fn is_json(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    essence == "application/json"
+        || essence
+            .strip_prefix("application/")
+            .is_some_and(|subtype| subtype.ends_with("+json"))
+}
+
+// Pulls the key out of serde's "unknown field `x`, expected ..." error so
+// clients with `deny_unknown_fields` payloads can see which key was a typo.
+fn unknown_field(message: &str) -> Option<&str> {
+    let (_, rest) = message.split_once("unknown field `")?;
+    let (field, _) = rest.split_once('`')?;
+    Some(field)
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::post, Router};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn send(content_type: &str, body: &'static str) -> (StatusCode, Value) {
+        let app = Router::new().route(
+            "/",
+            post(|ValidatedJson(value): ValidatedJson<Value>| async { Json(value) }),
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/")
+                    .header(header::CONTENT_TYPE, content_type)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn text_plain_test() {
+        let (status, body) = send("text/plain", r#"{"a":1}"#).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(body["code"], "unsupported_media_type");
+    }
+
+    #[tokio::test]
+    async fn empty_body_test() {
+        let (status, body) = send("application/json", "").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["message"], "Request body is empty");
+    }
+
+    #[tokio::test]
+    async fn json_with_charset_test() {
+        let (status, body) = send("application/json; charset=utf-8", r#"{"a":1}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["a"], 1);
+    }
+}
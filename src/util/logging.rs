@@ -0,0 +1,114 @@
+use std::str::FromStr;
+use tracing::{Level, Subscriber};
+use tracing_subscriber::{fmt::MakeWriter, util::SubscriberInitExt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    // One JSON object per line, with the fields of the request span (method,
+    // uri, request_id, route) next to the event's own fields.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "unknown log format {value:?}, expected pretty or json"
+            )),
+        }
+    }
+}
+
+// LOG_SQL logs at debug, so it lowers the level from the default info.
+pub fn init(format: LogFormat, log_sql: bool) {
+    let level = if log_sql { Level::DEBUG } else { Level::INFO };
+    subscriber(format, level, std::io::stdout).init();
+}
+
+fn subscriber<W>(format: LogFormat, level: Level, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Pretty => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(
+            builder
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(false)
+                .finish(),
+        ),
+    }
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("pretty".parse(), Ok(LogFormat::Pretty));
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert!("logfmt".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn json_test() {
+        let buffer = Buffer::default();
+        let subscriber = subscriber(LogFormat::Json, Level::INFO, buffer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span =
+                tracing::info_span!("request", request_id = "abc", route = tracing::field::Empty);
+            span.record("route", "/channel/:channelid");
+            span.in_scope(|| tracing::info!(status = 200, "finished processing request"));
+        });
+
+        let output = buffer.0.lock().unwrap();
+        let line: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["span"]["request_id"], "abc");
+        assert_eq!(line["span"]["route"], "/channel/:channelid");
+    }
+}
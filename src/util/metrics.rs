@@ -0,0 +1,63 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use deadpool_postgres::Pool;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::{
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+const REQUEST_DURATION: &str = "http_request_duration_seconds";
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+pub fn install() -> &'static PrometheusHandle {
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Full(REQUEST_DURATION.to_string()), LATENCY_BUCKETS)
+            .expect("Couldn't set metric buckets")
+            .install_recorder()
+            .expect("Couldn't install metrics recorder")
+    })
+}
+
+pub async fn track_metrics(request: Request, next: Next) -> Response {
+    let path = match request.extensions().get::<MatchedPath>() {
+        Some(path) => path.as_str().to_string(),
+        None => request.uri().path().to_string(),
+    };
+    let method = request.method().to_string();
+    // The request span is created before routing, so the route is filled in here.
+    tracing::Span::current().record("route", path.as_str());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let latency = start.elapsed().as_secs_f64();
+
+    let status = response.status().as_u16().to_string();
+    metrics::counter!("http_requests_total", "method" => method.clone(), "path" => path.clone(), "status" => status)
+        .increment(1);
+    metrics::histogram!(REQUEST_DURATION, "method" => method, "path" => path).record(latency);
+
+    response
+}
+
+pub async fn render(State(pool): State<Arc<Pool>>) -> impl IntoResponse {
+    let status = pool.status();
+    metrics::gauge!("db_pool_max_size").set(status.max_size as f64);
+    metrics::gauge!("db_pool_size").set(status.size as f64);
+    metrics::gauge!("db_pool_available").set(status.available as f64);
+    metrics::gauge!("db_pool_waiting").set(status.waiting as f64);
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        install().render(),
+    )
+}
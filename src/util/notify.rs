@@ -0,0 +1,208 @@
+use crate::util::{
+    db::{execute, in_current_scope, with_retry},
+    events::{self, ChannelEvent},
+};
+use deadpool_postgres::{GenericClient, Pool};
+use std::{
+    future::poll_fn,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio_postgres::{
+    tls::{MakeTlsConnect, TlsConnect},
+    AsyncMessage, Client, Config, Notification, Socket,
+};
+
+// The Postgres channel every replica LISTENs on. Payloads are the operation
+// and the channel id, e.g. "update:1234567890".
+pub const CHANNEL: &str = "channel_changes";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+static POOL: OnceLock<Arc<Pool>> = OnceLock::new();
+
+pub struct Listener {
+    // Dropping the client would end the session and with it the LISTEN.
+    _client: Client,
+    notifications: UnboundedReceiver<Notification>,
+}
+
+impl Listener {
+    // Relays notifications into the local broadcast until the connection drops.
+    pub async fn relay(mut self) {
+        while let Some(notification) = self.notifications.recv().await {
+            match parse(notification.payload()) {
+                Some(event) => events::deliver(event),
+                None => tracing::warn!(
+                    "Ignoring malformed {CHANNEL} payload {:?}",
+                    notification.payload()
+                ),
+            }
+        }
+    }
+}
+
+// Opens a dedicated session, since pooled ones are recycled between
+// requests, and returns once it is listening on CHANNEL.
+pub async fn listen<T>(config: &Config, tls: T) -> Result<Listener, tokio_postgres::Error>
+where
+    T: MakeTlsConnect<Socket>,
+    T::Stream: Send + 'static,
+{
+    let (client, mut connection) = config.connect(tls).await?;
+
+    let (sender, notifications) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(message) = poll_fn(|cx| connection.poll_message(cx)).await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    if sender.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::warn!("Lost the {CHANNEL} listener connection: {err}");
+                    break;
+                }
+            }
+        }
+    });
+
+    client.batch_execute(&format!("LISTEN {CHANNEL}")).await?;
+
+    Ok(Listener {
+        _client: client,
+        notifications,
+    })
+}
+
+// Keeps a listener relaying for the life of the process, reconnecting
+// whenever the session drops. Events sent while it is down are lost, the
+// same as for an SSE client that lags behind.
+pub fn spawn<T>(config: Config, tls: T)
+where
+    T: MakeTlsConnect<Socket> + Clone + Send + 'static,
+    T::Stream: Send + 'static,
+    T::TlsConnect: Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    tokio::spawn(async move {
+        loop {
+            match listen(&config, tls.clone()).await {
+                Ok(listener) => listener.relay().await,
+                Err(err) => tracing::warn!("Couldn't LISTEN on {CHANNEL}: {err}"),
+            }
+
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+// Routes events::publish through NOTIFY from now on. Call after spawn, or
+// this instance stops hearing about its own writes.
+pub fn enable(pool: Arc<Pool>) {
+    let _ = POOL.set(pool);
+}
+
+// NOTIFYs `event` in the background when the bridge is enabled. Returns
+// false when it isn't, so the caller delivers the event locally instead.
+pub fn forward(event: ChannelEvent) -> bool {
+    let Some(pool) = POOL.get() else {
+        return false;
+    };
+
+    let pool = Arc::clone(pool);
+    tokio::spawn(in_current_scope(async move {
+        let result = match with_retry(|| pool.get()).await {
+            Ok(con) => notify(&con, event).await.map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+
+        if let Err(err) = result {
+            tracing::warn!("Couldn't NOTIFY {CHANNEL}, delivering locally: {err}");
+            events::deliver(event);
+        }
+    }));
+
+    true
+}
+
+pub async fn notify(
+    client: &impl GenericClient,
+    event: ChannelEvent,
+) -> Result<(), tokio_postgres::Error> {
+    execute(
+        client,
+        "SELECT pg_notify($1, $2)",
+        &[&CHANNEL, &payload(event)],
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn payload(event: ChannelEvent) -> String {
+    format!("{}:{}", event.operation.as_str(), event.channel_id)
+}
+
+fn parse(payload: &str) -> Option<ChannelEvent> {
+    let (operation, channel_id) = payload.split_once(':')?;
+
+    Some(ChannelEvent {
+        operation: operation.parse().ok()?,
+        channel_id: channel_id.parse().ok()?,
+    })
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests, types::ChannelId, util::events::Operation};
+    use rand::random;
+    use tokio_postgres::NoTls;
+
+    #[test]
+    fn payload_test() {
+        let event = ChannelEvent {
+            operation: Operation::Delete,
+            channel_id: ChannelId(42),
+        };
+
+        assert_eq!(payload(event), "delete:42");
+        assert_eq!(parse("delete:42"), Some(event));
+        assert_eq!(parse("rename:42"), None);
+        assert_eq!(parse("delete"), None);
+        assert_eq!(parse("delete:abc"), None);
+    }
+
+    #[tokio::test]
+    async fn relay_test() {
+        // The writer and the listener stand in for two replicas, each with
+        // its own connections.
+        let writer = tests::pool();
+        let config = tests::db_config().get_pg_config().unwrap();
+        let listener = listen(&config, NoTls).await.unwrap();
+        tokio::spawn(listener.relay());
+        let mut receiver = events::subscribe();
+
+        let event = ChannelEvent {
+            operation: Operation::Update,
+            channel_id: ChannelId(random()),
+        };
+        let con = writer.get().await.unwrap();
+        notify(&con, event).await.unwrap();
+
+        // Other tests may publish too, so skip anything that isn't ours.
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            while receiver.recv().await != Ok(event) {}
+        })
+        .await;
+        assert!(received.is_ok(), "no notification for {}", event.channel_id);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::Message;
+use axum::{
+    async_trait,
+    extract::{path::ErrorKind, rejection::PathRejection, FromRequestParts, Path, RawPathParams},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+// Drop-in replacement for `axum::extract::Path` that reports unparsable
+// segments as a 400 Message such as "invalid channel id".
+pub(crate) struct ValidatedPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ValidatedPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<Message>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(value)) => Ok(ValidatedPath(value)),
+            Err(PathRejection::FailedToDeserializePathParams(err)) => {
+                // A single-value Path doesn't report which key failed, so
+                // look it up from the raw params by the offending value.
+                let key = match err.kind() {
+                    ErrorKind::ParseErrorAtKey { key, .. } => Some(key.clone()),
+                    ErrorKind::ParseError { value, .. } => {
+                        RawPathParams::from_request_parts(parts, state)
+                            .await
+                            .ok()
+                            .and_then(|params| {
+                                params
+                                    .iter()
+                                    .find(|(_, raw)| raw == value)
+                                    .map(|(key, _)| key.to_string())
+                            })
+                    }
+                    _ => None,
+                };
+                let message = match key {
+                    Some(key) => format!("invalid {}", param_name(&key)),
+                    None => "invalid id".to_string(),
+                };
+
+                let msg = Message {
+                    message,
+                    code: Some("validation_error".to_string()),
+                    ..Default::default()
+                };
+
+                Err((StatusCode::BAD_REQUEST, Json(msg)))
+            }
+            Err(rejection) => {
+                let msg = Message {
+                    message: rejection.body_text(),
+                    code: Some("internal_error".to_string()),
+                    ..Default::default()
+                };
+
+                Err((rejection.status(), Json(msg)))
+            }
+        }
+    }
+}
+
+fn param_name(key: &str) -> &str {
+    match key {
+        "channelid" => "channel id",
+        "guildid" => "guild id",
+        "to" => "target channel id",
+        other => other,
+    }
+}
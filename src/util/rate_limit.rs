@@ -0,0 +1,73 @@
+use crate::Message;
+use axum::{
+    body::Body,
+    http::{header, Method, Response, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use governor::middleware::NoOpMiddleware;
+use std::{sync::Arc, thread, time::Duration};
+use tower_governor::{
+    governor::GovernorConfigBuilder, key_extractor::PeerIpKeyExtractor, GovernorError,
+    GovernorLayer,
+};
+
+pub fn rate_limit_layer(
+    requests_per_second: u64,
+) -> GovernorLayer<PeerIpKeyExtractor, NoOpMiddleware> {
+    let config = GovernorConfigBuilder::default()
+        .per_nanosecond(1_000_000_000 / requests_per_second.max(1))
+        .burst_size(u32::try_from(requests_per_second * 2).unwrap_or(u32::MAX))
+        .methods(vec![
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .error_handler(too_many_requests)
+        .finish()
+        .expect("Couldn't build rate limiter");
+
+    // Forget clients that have stopped sending requests so the limiter doesn't
+    // grow without bound. The thread exits once the layer is dropped.
+    let limiter = Arc::downgrade(config.limiter());
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(60));
+        match limiter.upgrade() {
+            Some(limiter) => limiter.retain_recent(),
+            None => break,
+        }
+    });
+
+    GovernorLayer {
+        config: Arc::new(config),
+    }
+}
+
+fn too_many_requests(err: GovernorError) -> Response<Body> {
+    match err {
+        GovernorError::TooManyRequests { wait_time, .. } => {
+            let msg = Message {
+                message: format!("Too many requests, retry in {}s", wait_time.max(1)),
+                code: Some("rate_limited".to_string()),
+                ..Default::default()
+            };
+
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, wait_time.max(1).to_string())],
+                Json(msg),
+            )
+                .into_response()
+        }
+        GovernorError::UnableToExtractKey | GovernorError::Other { .. } => {
+            let msg = Message {
+                message: "INTERNAL SERVER ERROR".to_string(),
+                code: Some("internal_error".to_string()),
+                ..Default::default()
+            };
+
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(msg)).into_response()
+        }
+    }
+}
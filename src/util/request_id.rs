@@ -0,0 +1,38 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+use tracing::Span;
+
+pub const X_REQUEST_ID: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+// Makes the ID set by `SetRequestIdLayer` available to `current` for the rest
+// of the request, so error responses can include it without every handler
+// having to extract it.
+pub async fn scope_request_id(request: Request, next: Next) -> Response {
+    let id = header_value(&request).unwrap_or_default();
+    REQUEST_ID.scope(id, next.run(request)).await
+}
+
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+pub fn make_span(request: &Request) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = header_value(request).unwrap_or_default(),
+        route = tracing::field::Empty,
+    )
+}
+
+fn header_value(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
@@ -0,0 +1,167 @@
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use std::{fs::File, io::BufReader, path::Path, str::FromStr, sync::Arc};
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SslMode {
+    #[default]
+    Disable,
+    // Encrypt the connection without checking who is on the other end.
+    Require,
+    // Encrypt and verify the certificate chain and host name.
+    VerifyFull,
+}
+
+impl FromStr for SslMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "disable" => Ok(SslMode::Disable),
+            "require" => Ok(SslMode::Require),
+            "verify-full" => Ok(SslMode::VerifyFull),
+            _ => Err(format!(
+                "unknown sslmode {value:?}, expected disable, require or verify-full"
+            )),
+        }
+    }
+}
+
+// Returns `None` when TLS is disabled so callers can fall back to `NoTls`.
+pub fn connector(
+    mode: SslMode,
+    ca_cert: Option<&Path>,
+) -> Result<Option<MakeRustlsConnect>, String> {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|err| err.to_string())?;
+
+    let config = match mode {
+        SslMode::Disable => return Ok(None),
+        SslMode::Require => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+            .with_no_client_auth(),
+        SslMode::VerifyFull => builder
+            .with_root_certificates(root_store(ca_cert)?)
+            .with_no_client_auth(),
+    };
+
+    Ok(Some(MakeRustlsConnect::new(config)))
+}
+
+fn root_store(ca_cert: Option<&Path>) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+
+    let Some(path) = ca_cert else {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        return Ok(roots);
+    };
+
+    let file = File::open(path)
+        .map_err(|err| format!("Couldn't open DB_CA_CERT {}: {err}", path.display()))?;
+    for cert in rustls_pemfile::certs(&mut BufReader::new(file)) {
+        let cert =
+            cert.map_err(|err| format!("Couldn't parse DB_CA_CERT {}: {err}", path.display()))?;
+        roots.add(cert).map_err(|err| {
+            format!(
+                "Invalid certificate in DB_CA_CERT {}: {err}",
+                path.display()
+            )
+        })?;
+    }
+
+    if roots.is_empty() {
+        return Err(format!(
+            "DB_CA_CERT {} contains no certificates",
+            path.display()
+        ));
+    }
+
+    Ok(roots)
+}
+
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("disable".parse(), Ok(SslMode::Disable));
+        assert_eq!("require".parse(), Ok(SslMode::Require));
+        assert_eq!("verify-full".parse(), Ok(SslMode::VerifyFull));
+        assert!("verify-ca".parse::<SslMode>().is_err());
+    }
+
+    #[test]
+    fn connector_test() {
+        assert!(connector(SslMode::Disable, None).unwrap().is_none());
+        assert!(connector(SslMode::Require, None).unwrap().is_some());
+        assert!(connector(SslMode::VerifyFull, None).unwrap().is_some());
+    }
+
+    #[test]
+    fn invalid_ca_cert_test() {
+        let path = Path::new("/does/not/exist.pem");
+        let err = connector(SslMode::VerifyFull, Some(path)).err().unwrap();
+        assert!(err.contains("/does/not/exist.pem"), "{err}");
+    }
+}
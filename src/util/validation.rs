@@ -0,0 +1,327 @@
+use crate::{util::i18n, Message};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+pub const DEFAULT_MAX_BULK_ITEMS: usize = 1000;
+
+// The timestamp in a snowflake's top 42 bits counts milliseconds from the
+// start of 2015.
+pub const DISCORD_EPOCH_MS: i64 = 1_420_070_400_000;
+
+tokio::task_local! {
+    // Follows ENABLE_ADMIN_ENDPOINTS, see FieldErrors::admin_only.
+    static ADMIN_FIELDS: bool;
+    // Follows MAX_BULK_ITEMS, see FieldErrors::max_items.
+    static MAX_BULK_ITEMS: usize;
+}
+
+// Scoped per request like types::scope_snowflakes_as_strings, so tests can
+// build apps with and without it side by side.
+pub async fn scope_admin_fields(
+    State(enabled): State<bool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    ADMIN_FIELDS.scope(enabled, next.run(request)).await
+}
+
+pub async fn scope_max_bulk_items(
+    State(max): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    MAX_BULK_ITEMS.scope(max, next.run(request)).await
+}
+
+// Discord snowflakes are always positive; BIGINT columns let anything else
+// through, so reject it before it reaches the database.
+#[allow(clippy::result_large_err)]
+pub fn validate_snowflake(
+    field: &str,
+    value: impl Into<i64>,
+) -> Result<(), (StatusCode, Json<Message>)> {
+    let value = value.into();
+    if value > 0 {
+        return Ok(());
+    }
+
+    let msg = Message {
+        message: i18n::not_positive_snowflake(field, value),
+        code: Some("validation_error".to_string()),
+        ..Default::default()
+    };
+
+    Err((StatusCode::BAD_REQUEST, Json(msg)))
+}
+
+pub fn snowflake_timestamp(value: i64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis((value >> 22) + DISCORD_EPOCH_MS)
+}
+
+// Every positive i64 decodes to some time between 2015 and 2084, but
+// Discord can't have minted one at the very epoch or in the future. Those
+// are typos or made-up IDs rather than real ones.
+#[allow(clippy::result_large_err)]
+pub fn validate_snowflake_epoch(
+    field: &str,
+    value: impl Into<i64>,
+) -> Result<(), (StatusCode, Json<Message>)> {
+    let value = value.into();
+    if is_minted(value) {
+        return Ok(());
+    }
+
+    let msg = Message {
+        message: i18n::not_minted_snowflake(field, value),
+        code: Some("validation_error".to_string()),
+        ..Default::default()
+    };
+
+    Err((StatusCode::BAD_REQUEST, Json(msg)))
+}
+
+fn is_minted(value: i64) -> bool {
+    value >> 22 > 0 && snowflake_timestamp(value).is_some_and(|timestamp| timestamp <= Utc::now())
+}
+
+// Collects every problem with a payload so clients can fix them in one go,
+// e.g. `FieldErrors::default().snowflake("guild_id", id).finish()`.
+#[derive(Default)]
+pub struct FieldErrors(Vec<String>);
+
+impl FieldErrors {
+    pub fn snowflake(mut self, field: &str, value: impl Into<i64>) -> Self {
+        if value.into() <= 0 {
+            self.0.push(format!("{field}: must be positive"));
+        }
+        self
+    }
+
+    // Same check as validate_snowflake_epoch. Values that aren't positive are
+    // left to `snowflake`, so they aren't reported twice.
+    pub fn minted(mut self, field: &str, value: impl Into<i64>) -> Self {
+        let value = value.into();
+        if value > 0 && !is_minted(value) {
+            self.0
+                .push(format!("{field}: timestamp isn't between 2015 and now"));
+        }
+        self
+    }
+
+    pub fn not_empty(mut self, field: &str, value: &str) -> Self {
+        if value.trim().is_empty() {
+            self.0.push(format!("{field}: empty"));
+        }
+        self
+    }
+
+    // Counts characters rather than bytes, matching how Discord limits names.
+    pub fn max_chars(mut self, field: &str, value: &str, max: usize) -> Self {
+        if value.chars().count() > max {
+            self.0
+                .push(format!("{field}: longer than {max} characters"));
+        }
+        self
+    }
+
+    // Caps the arrays bulk endpoints accept, so one request can't hold a lock
+    // on the table for long. Check it before validating the items themselves.
+    pub fn max_items(mut self, field: &str, len: usize) -> Self {
+        let max = MAX_BULK_ITEMS
+            .try_with(|max| *max)
+            .unwrap_or(DEFAULT_MAX_BULK_ITEMS);
+        if len > max {
+            self.0.push(format!("{field}: more than {max} items"));
+        }
+        self
+    }
+
+    // Fields that rewrite history, like an imported added_at, are only
+    // trusted from deployments running with the admin endpoints enabled.
+    pub fn admin_only(mut self, field: &str, present: bool) -> Self {
+        if present && !ADMIN_FIELDS.try_with(|enabled| *enabled).unwrap_or(false) {
+            self.0
+                .push(format!("{field}: requires ENABLE_ADMIN_ENDPOINTS=true"));
+        }
+        self
+    }
+
+    // Each entry of an array of subscriptions, reported by index, e.g.
+    // anilist[2].site_url. See anilist::validate_site_url.
+    pub fn site_urls<'a>(mut self, field: &str, values: impl Iterator<Item = &'a str>) -> Self {
+        for (index, value) in values.enumerate() {
+            if crate::anilist::validate_site_url(value).is_err() {
+                self.0.push(format!(
+                    "{field}[{index}].site_url: not an https://anilist.co URL"
+                ));
+            }
+        }
+        self
+    }
+
+    pub fn one_of<T: PartialEq + fmt::Display>(
+        mut self,
+        field: &str,
+        value: T,
+        allowed: &[T],
+    ) -> Self {
+        if !allowed.contains(&value) {
+            self.0
+                .push(format!("{field}: {value} is not one of the allowed values"));
+        }
+        self
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn finish(self) -> Result<(), (StatusCode, Json<Message>)> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+
+        let msg = Message {
+            message: i18n::invalid_fields(self.0.len()),
+            code: Some("validation_error".to_string()),
+            data: Some(self.0),
+            ..Default::default()
+        };
+
+        Err((StatusCode::UNPROCESSABLE_ENTITY, Json(msg)))
+    }
+}
+
+// ------------------------------------------------
+// Testing
+// ------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_test() {
+        let (status, Json(msg)) = validate_snowflake("channel_id", 0).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(msg.message.contains("channel_id"));
+    }
+
+    #[test]
+    fn negative_test() {
+        let (status, Json(msg)) = validate_snowflake("guild_id", -42).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(msg.message.contains("guild_id"));
+    }
+
+    #[test]
+    fn large_test() {
+        assert!(validate_snowflake("added_by", i64::MAX - 1).is_ok());
+        assert!(validate_snowflake("added_by", 1_234_567_890_123_456_789_i64).is_ok());
+    }
+
+    #[test]
+    fn epoch_test() {
+        // Discord's own example, minted 2016-04-30T11:18:25.796Z.
+        assert!(validate_snowflake_epoch("guild_id", 175_928_847_299_117_063_i64).is_ok());
+        assert_eq!(
+            snowflake_timestamp(175_928_847_299_117_063)
+                .unwrap()
+                .to_rfc3339(),
+            "2016-04-30T11:18:25.796+00:00"
+        );
+
+        for value in [7, (1 << 22) - 1, i64::MAX] {
+            let (status, Json(msg)) = validate_snowflake_epoch("guild_id", value).unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(msg.message.contains("guild_id"), "{value}");
+        }
+    }
+
+    #[test]
+    fn field_errors_test() {
+        let (status, Json(msg)) = FieldErrors::default()
+            .not_empty("channel_name", " ")
+            .snowflake("guild_id", 0)
+            .snowflake("added_by", 1)
+            .finish()
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            msg.data.unwrap(),
+            ["channel_name: empty", "guild_id: must be positive"]
+        );
+
+        assert!(FieldErrors::default()
+            .snowflake("guild_id", 1)
+            .finish()
+            .is_ok());
+    }
+
+    #[test]
+    fn max_chars_test() {
+        assert!(FieldErrors::default()
+            .max_chars("guild_name", "ééé", 3)
+            .finish()
+            .is_ok());
+
+        let (_, Json(msg)) = FieldErrors::default()
+            .max_chars("guild_name", "abcd", 3)
+            .finish()
+            .unwrap_err();
+        assert_eq!(msg.data.unwrap(), ["guild_name: longer than 3 characters"]);
+    }
+
+    #[test]
+    fn max_items_test() {
+        assert!(FieldErrors::default()
+            .max_items("channels", DEFAULT_MAX_BULK_ITEMS)
+            .finish()
+            .is_ok());
+
+        let (status, Json(msg)) = FieldErrors::default()
+            .max_items("channels", DEFAULT_MAX_BULK_ITEMS + 1)
+            .finish()
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(msg.data.unwrap(), ["channels: more than 1000 items"]);
+    }
+
+    #[tokio::test]
+    async fn max_items_scoped_test() {
+        let errors = MAX_BULK_ITEMS
+            .scope(2, async {
+                FieldErrors::default().max_items("channels", 3).finish()
+            })
+            .await;
+        let (_, Json(msg)) = errors.unwrap_err();
+        assert_eq!(msg.data.unwrap(), ["channels: more than 2 items"]);
+
+        // Other apps in the same process keep their own limit.
+        assert!(FieldErrors::default()
+            .max_items("channels", 3)
+            .finish()
+            .is_ok());
+    }
+
+    #[test]
+    fn one_of_test() {
+        assert!(FieldErrors::default()
+            .one_of("channel_id", 2, &[1, 2])
+            .finish()
+            .is_ok());
+
+        let (_, Json(msg)) = FieldErrors::default()
+            .one_of("channel_id", 3, &[1, 2])
+            .finish()
+            .unwrap_err();
+        assert_eq!(
+            msg.data.unwrap(),
+            ["channel_id: 3 is not one of the allowed values"]
+        );
+    }
+}